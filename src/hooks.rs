@@ -0,0 +1,63 @@
+use alloc::sync::Arc;
+
+use lazyinit::LazyInit;
+
+use crate::{Process, ProcessGroup};
+
+/// A callback notified about a pair of [`Process`]es.
+pub type ProcessHook = fn(&Arc<Process>, &Arc<Process>);
+
+/// A callback notified about a [`ProcessGroup`].
+pub type GroupHook = fn(&Arc<ProcessGroup>);
+
+static ON_REPARENT: LazyInit<ProcessHook> = LazyInit::new();
+static ON_CHILD_EXIT: LazyInit<ProcessHook> = LazyInit::new();
+static ON_HANGUP: LazyInit<GroupHook> = LazyInit::new();
+
+/// Sets the callback invoked for each child reparented by
+/// [`Process::exit`], with the new parent and the child.
+///
+/// The callback is invoked with no lock held. It can only be set once.
+/// Returns `false` if it was already set.
+pub fn on_reparent(f: ProcessHook) -> bool {
+    ON_REPARENT.call_once(|| f).is_some()
+}
+
+pub(crate) fn reparented(parent: &Arc<Process>, child: &Arc<Process>) {
+    if let Some(f) = ON_REPARENT.get() {
+        f(parent, child);
+    }
+}
+
+/// Sets the callback invoked at the end of [`Process::exit`], with the parent
+/// and the exited child.
+///
+/// The callback is invoked with no lock held, including when the parent is
+/// the init process. It can only be set once. Returns `false` if it was
+/// already set.
+pub fn on_child_exit(f: ProcessHook) -> bool {
+    ON_CHILD_EXIT.call_once(|| f).is_some()
+}
+
+pub(crate) fn child_exited(parent: &Arc<Process>, child: &Arc<Process>) {
+    if let Some(f) = ON_CHILD_EXIT.get() {
+        f(parent, child);
+    }
+}
+
+/// Sets the callback invoked by [`Process::exit`] on a session leader, for
+/// each [`ProcessGroup`] of its [`Session`](crate::Session) that should be
+/// hung up, i.e. sent `SIGHUP`.
+///
+/// The callback is invoked with no lock held, after the terminal has been
+/// disassociated. It can only be set once. Returns `false` if it was already
+/// set.
+pub fn on_hangup(f: GroupHook) -> bool {
+    ON_HANGUP.call_once(|| f).is_some()
+}
+
+pub(crate) fn hangup(group: &Arc<ProcessGroup>) {
+    if let Some(f) = ON_HANGUP.get() {
+        f(group);
+    }
+}
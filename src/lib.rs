@@ -5,13 +5,26 @@
 
 extern crate alloc;
 
+mod hooks;
+mod pid_allocator;
 mod process;
 mod process_group;
 mod session;
+mod table;
 
 /// A process ID, also used as session ID, process group ID, and thread ID.
 pub type Pid = u32;
 
-pub use process::{Process, init_proc};
+pub use hooks::{GroupHook, ProcessHook, on_child_exit, on_hangup, on_reparent};
+pub use pid_allocator::PidAllocator;
+pub use process::{
+    CollapsedThreads, Credentials, NotZombie, PidInUse, Placement, Process, ProcessData,
+    ProcessState, WaitStatus, init_proc,
+};
 pub use process_group::ProcessGroup;
 pub use session::Session;
+pub use table::{
+    ProcessGroupTableGuard, ProcessTableGuard, SessionTableGuard, get_process, get_process_group,
+    get_process_group_gen, get_session, get_session_gen, init_process, orphaned_groups,
+    process_group_table, process_table, session_table, set_init_process,
+};
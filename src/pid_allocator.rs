@@ -0,0 +1,85 @@
+use alloc::{vec, vec::Vec};
+
+use kspin::SpinNoIrq;
+
+use crate::{Pid, table};
+
+struct Bitmap {
+    bits: Vec<u64>,
+    next: Pid,
+}
+
+impl Bitmap {
+    fn get(&self, pid: Pid) -> bool {
+        self.bits[pid as usize / 64] & (1 << (pid % 64)) != 0
+    }
+
+    fn set(&mut self, pid: Pid, value: bool) {
+        let word = &mut self.bits[pid as usize / 64];
+        if value {
+            *word |= 1 << (pid % 64);
+        } else {
+            *word &= !(1 << (pid % 64));
+        }
+    }
+}
+
+/// An allocator of [`Pid`]s.
+///
+/// [`Pid`]s are allocated from `1..=max_pid`, in increasing order starting
+/// after the last allocated one, and wrap around once `max_pid` is reached.
+/// Freed [`Pid`]s can be allocated again, but a [`Pid`] that is still used by
+/// a [`Process`](crate::Process) in the process table, or as the ID of a live
+/// [`ProcessGroup`](crate::ProcessGroup) or [`Session`](crate::Session), is
+/// never returned.
+pub struct PidAllocator {
+    max_pid: Pid,
+    bitmap: SpinNoIrq<Bitmap>,
+}
+
+impl PidAllocator {
+    /// Creates a [`PidAllocator`] that allocates [`Pid`]s up to `max_pid`.
+    pub fn new(max_pid: Pid) -> Self {
+        Self {
+            max_pid,
+            bitmap: SpinNoIrq::new(Bitmap {
+                bits: vec![0; max_pid as usize / 64 + 1],
+                next: 1,
+            }),
+        }
+    }
+
+    /// The largest [`Pid`] that can be allocated.
+    pub fn max_pid(&self) -> Pid {
+        self.max_pid
+    }
+
+    /// Allocates a [`Pid`].
+    ///
+    /// Returns `None` if all [`Pid`]s are in use.
+    pub fn alloc(&self) -> Option<Pid> {
+        let mut bitmap = self.bitmap.lock();
+        let processes = table::process_table();
+        let groups = table::process_group_table();
+        let sessions = table::session_table();
+        let in_use = |pid| {
+            processes.contains_key(&pid) || groups.contains_key(&pid) || sessions.contains_key(&pid)
+        };
+
+        let start = bitmap.next;
+        let pid = (start..=self.max_pid)
+            .chain(1..start)
+            .find(|&pid| !bitmap.get(pid) && !in_use(pid))?;
+        bitmap.set(pid, true);
+        bitmap.next = if pid == self.max_pid { 1 } else { pid + 1 };
+        Some(pid)
+    }
+
+    /// Frees a [`Pid`] allocated by [`PidAllocator::alloc`], so that it can
+    /// be allocated again.
+    pub fn free(&self, pid: Pid) {
+        if (1..=self.max_pid).contains(&pid) {
+            self.bitmap.lock().set(pid, false);
+        }
+    }
+}
@@ -1,52 +1,266 @@
 use alloc::{
-    collections::btree_set::BTreeSet,
+    collections::{btree_map::BTreeMap, btree_set::BTreeSet, vec_deque::VecDeque},
     sync::{Arc, Weak},
     vec::Vec,
 };
 use core::{
-    fmt,
-    sync::atomic::{AtomicBool, Ordering},
+    any::{Any, TypeId},
+    fmt, ptr,
+    sync::atomic::{AtomicBool, AtomicI32, AtomicU8, AtomicU64, AtomicUsize, Ordering},
 };
 
 use kspin::SpinNoIrq;
-use lazyinit::LazyInit;
 use weak_map::StrongMap;
 
-use crate::{Pid, ProcessGroup, Session};
+use crate::{Pid, ProcessGroup, Session, hooks, table};
 
 #[derive(Default)]
 pub(crate) struct ThreadGroup {
     pub(crate) threads: BTreeSet<Pid>,
-    pub(crate) exit_code: i32,
-    pub(crate) group_exited: bool,
+    pub(crate) leader_exited: bool,
 }
 
+/// The placement of a [`Process`] in the [`ProcessGroup`] & [`Session`]
+/// hierarchy, as saved by [`Process::placement`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placement {
+    /// The [`ProcessGroup`] ID.
+    pub pgid: Pid,
+    /// The [`Session`] ID.
+    pub sid: Pid,
+}
+
+/// The threads left to destroy by [`Process::collapse_to_thread`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollapsedThreads {
+    /// The IDs of the threads other than the survivor and the old group
+    /// leader.
+    pub threads: Vec<Pid>,
+    /// Whether the old group leader thread was still alive, in which case it
+    /// has to be destroyed too. Its ID is not in `threads`, as it now belongs
+    /// to the survivor. Always `false` if the survivor was the group leader.
+    pub leader_alive: bool,
+}
+
+/// The user & group IDs of a [`Process`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Credentials {
+    /// The real user ID.
+    pub ruid: u32,
+    /// The effective user ID.
+    pub euid: u32,
+    /// The real group ID.
+    pub rgid: u32,
+    /// The effective group ID.
+    pub egid: u32,
+}
+
+/// A handle to the data of type `T` associated with a [`Process`], as
+/// returned by [`Process::data`].
+///
+/// All handles to the same data share it, so changes made through one are
+/// seen through the others.
+pub struct ProcessData<T>(Arc<SpinNoIrq<T>>);
+
+impl<T> ProcessData<T> {
+    /// Calls `f` with shared access to the data, and returns its result.
+    ///
+    /// The data is locked while `f` is called, so `f` must not access it
+    /// again.
+    pub fn with<R>(&self, f: impl FnOnce(&T) -> R) -> R {
+        f(&self.0.lock())
+    }
+
+    /// Calls `f` with mutable access to the data, and returns its result.
+    ///
+    /// The data is locked while `f` is called, so `f` must not access it
+    /// again.
+    pub fn with_mut<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut self.0.lock())
+    }
+}
+
+impl<T> Clone for ProcessData<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// The error returned by [`Process::try_fork`] when the requested [`Pid`] is
+/// already used by a [`Process`] in the process table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PidInUse(pub Pid);
+
+impl fmt::Display for PidInUse {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "pid {} is already in use", self.0)
+    }
+}
+
+impl core::error::Error for PidInUse {}
+
+/// The error returned by [`Process::try_free`] when the [`Process`] is not a
+/// zombie, or has already been freed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NotZombie;
+
+impl fmt::Display for NotZombie {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("only zombie process can be freed")
+    }
+}
+
+impl core::error::Error for NotZombie {}
+
+/// How a [`Process`] changed state, as reported by `wait`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WaitStatus {
+    /// The [`Process`] exited normally with the given exit code.
+    Exited(u8),
+    /// The [`Process`] was terminated by a signal.
+    Signaled {
+        /// The signal number.
+        sig: u8,
+        /// Whether a core dump was produced.
+        core_dumped: bool,
+    },
+    /// The [`Process`] was stopped by the given signal.
+    Stopped(u8),
+    /// The [`Process`] was continued by `SIGCONT`.
+    Continued,
+}
+
+impl WaitStatus {
+    /// Packs the [`WaitStatus`] into the `int` layout expected by userspace,
+    /// as decoded by `WIFEXITED`, `WTERMSIG`, etc.
+    pub fn to_raw(self) -> i32 {
+        match self {
+            Self::Exited(code) => (code as i32) << 8,
+            Self::Signaled { sig, core_dumped } => {
+                (sig & 0x7f) as i32 | if core_dumped { 0x80 } else { 0 }
+            }
+            Self::Stopped(sig) => (sig as i32) << 8 | 0x7f,
+            Self::Continued => 0xffff,
+        }
+    }
+
+    /// Unpacks a [`WaitStatus`] from the `int` layout expected by userspace.
+    pub fn from_raw(raw: i32) -> Self {
+        let raw = raw & 0xffff;
+        if raw == 0xffff {
+            Self::Continued
+        } else if raw & 0xff == 0x7f {
+            Self::Stopped((raw >> 8) as u8)
+        } else if raw & 0x7f == 0 {
+            Self::Exited((raw >> 8) as u8)
+        } else {
+            Self::Signaled {
+                sig: (raw & 0x7f) as u8,
+                core_dumped: raw & 0x80 != 0,
+            }
+        }
+    }
+}
+
+/// The state of a [`Process`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ProcessState {
+    /// The [`Process`] is running.
+    Running,
+    /// The [`Process`] is stopped, e.g. by `SIGSTOP`.
+    Stopped,
+    /// The [`Process`] has exited but has not been freed yet.
+    Zombie,
+}
+
+impl ProcessState {
+    fn from_raw(raw: u8) -> Self {
+        match raw {
+            0 => Self::Running,
+            1 => Self::Stopped,
+            2 => Self::Zombie,
+            _ => unreachable!("invalid process state {raw}"),
+        }
+    }
+}
+
+// Lock ordering: a `Process`'s locks are taken before those of its
+// `ProcessGroup`, which are taken before those of its `Session`, which are
+// taken before the global tables. Within a level:
+//
+// - `exit` locks its own `children` before those of its reaper, which is an
+//   ancestor, and then the `parent` of each child. The reaper is picked with
+//   the `children` locked, which locks the `parent` of each ancestor in turn.
+// - `switch_group` locks the `Process`'s `group` before the member maps of both
+//   `ProcessGroup`s, which are locked in address order.
+// - `PidAllocator::alloc` locks the process, process group and session tables
+//   at once, in that order.
+//
+// No path holds `children` and `group` at the same time, and `free` takes
+// `parent`, the parent's `children` and the process table one at a time.
+// Hooks are called with no lock held.
+
 /// A process.
 pub struct Process {
     pid: Pid,
-    is_zombie: AtomicBool,
+    start_seq: u64,
+    state: AtomicU8,
     pub(crate) tg: SpinNoIrq<ThreadGroup>,
+    // Only written with `tg` locked, so that the exit code is never
+    // overwritten after the group has exited.
+    exit_code: AtomicI32,
+    group_exited: AtomicBool,
+    exit_status: SpinNoIrq<Option<WaitStatus>>,
 
-    // TODO: child subreaper9
+    is_child_subreaper: AtomicBool,
     children: SpinNoIrq<StrongMap<Pid, Arc<Process>>>,
     parent: SpinNoIrq<Weak<Process>>,
 
     group: SpinNoIrq<Arc<ProcessGroup>>,
+
+    loginuid: SpinNoIrq<Option<u32>>,
+    credentials: SpinNoIrq<Credentials>,
+
+    data: SpinNoIrq<BTreeMap<TypeId, Arc<dyn Any + Send + Sync>>>,
 }
 
+static START_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// The number of live [`Process`]es that are child subreapers, so that
+/// [`Process::reaper`] can skip searching the ancestors if there is none.
+static CHILD_SUBREAPERS: AtomicUsize = AtomicUsize::new(0);
+
 impl Process {
     /// The [`Process`] ID.
     pub fn pid(&self) -> Pid {
         self.pid
     }
 
+    /// The creation sequence number of the [`Process`].
+    ///
+    /// Unlike the [`Process`] ID, which may be reused, the sequence number is
+    /// unique among all [`Process`]es ever created and increases with
+    /// creation order.
+    pub fn start_seq(&self) -> u64 {
+        self.start_seq
+    }
+
+    /// Returns the number of strong references to the [`Process`].
+    ///
+    /// This is only a hint meant for debugging leaks, as other CPUs may
+    /// acquire or release references at any time.
+    pub fn strong_count_hint(self: &Arc<Self>) -> usize {
+        Arc::strong_count(self)
+    }
+
     /// Returns `true` if the [`Process`] is the init process.
     ///
     /// This is a convenience method for checking if the [`Process`]
     /// [`Arc::ptr_eq`]s with the init process, which is cheaper than
     /// calling [`init_proc`] or testing if [`Process::parent`] is `None`.
     pub fn is_init(self: &Arc<Self>) -> bool {
-        Arc::ptr_eq(self, INIT_PROC.get().unwrap())
+        table::init_process_ref().is_some_and(|init| Arc::ptr_eq(self, init))
     }
 }
 
@@ -61,6 +275,115 @@ impl Process {
     pub fn children(&self) -> Vec<Arc<Process>> {
         self.children.lock().values().cloned().collect()
     }
+
+    /// Calls `f` on each child [`Process`] that matches `pred`.
+    ///
+    /// Only the matching children are collected. `pred` is called with the
+    /// children locked, so it must not access the children of this
+    /// [`Process`]; `f` is called after the lock is released.
+    pub fn children_where(
+        &self,
+        pred: impl Fn(&Arc<Process>) -> bool,
+        f: impl FnMut(&Arc<Process>),
+    ) {
+        let matched: Vec<_> = self
+            .children
+            .lock()
+            .values()
+            .filter(|c| pred(c))
+            .cloned()
+            .collect();
+        matched.iter().for_each(f);
+    }
+
+    /// Finds the first child [`Process`] that matches `pred`.
+    ///
+    /// `pred` is called with the children locked, so it must not access the
+    /// children of this [`Process`].
+    pub fn find_child(&self, pred: impl Fn(&Arc<Process>) -> bool) -> Option<Arc<Process>> {
+        self.children.lock().values().find(|c| pred(c)).cloned()
+    }
+
+    /// Finds a zombie child [`Process`], without freeing it.
+    pub fn find_zombie_child(&self) -> Option<Arc<Process>> {
+        self.find_child(|c| c.is_zombie())
+    }
+
+    /// Finds the child [`Process`] with the given ID if it is a zombie,
+    /// without freeing it.
+    pub fn find_zombie_child_by_pid(&self, pid: Pid) -> Option<Arc<Process>> {
+        self.children
+            .lock()
+            .get(&pid)
+            .filter(|c| c.is_zombie())
+            .cloned()
+    }
+
+    /// Calls `f` on the [`Process`] and all of its descendants, in
+    /// breadth-first order.
+    ///
+    /// No lock is held while `f` is called. Each [`Process`] is visited at
+    /// most once, even if the tree were to contain a cycle.
+    fn walk_subtree(self: &Arc<Self>, mut f: impl FnMut(&Arc<Process>)) {
+        let mut visited = BTreeSet::from([self.pid]);
+        let mut queue = VecDeque::from([self.clone()]);
+        while let Some(process) = queue.pop_front() {
+            f(&process);
+            queue.extend(
+                process
+                    .children()
+                    .into_iter()
+                    .filter(|c| visited.insert(c.pid)),
+            );
+        }
+    }
+
+    /// Calls `f` on each descendant of the [`Process`], in breadth-first
+    /// order.
+    ///
+    /// No lock is held while `f` is called.
+    pub fn for_each_descendant(self: &Arc<Self>, mut f: impl FnMut(&Arc<Process>)) {
+        self.walk_subtree(|p| {
+            if !ptr::eq(&**p, &**self) {
+                f(p);
+            }
+        });
+    }
+
+    /// The descendants of the [`Process`], in breadth-first order.
+    pub fn descendants(self: &Arc<Self>) -> Vec<Arc<Process>> {
+        let mut descendants = Vec::new();
+        self.for_each_descendant(|p| descendants.push(p.clone()));
+        descendants
+    }
+
+    /// The IDs of all descendants of the [`Process`].
+    pub fn descendant_pids(self: &Arc<Self>) -> BTreeSet<Pid> {
+        let mut pids = BTreeSet::new();
+        self.for_each_descendant(|p| {
+            pids.insert(p.pid);
+        });
+        pids
+    }
+
+    /// Returns an iterator over the ancestors of the [`Process`], starting
+    /// from its parent and ending at the init process.
+    ///
+    /// The iteration stops early if a parent is gone.
+    pub fn ancestors(&self) -> impl Iterator<Item = Arc<Process>> {
+        core::iter::successors(self.parent(), |p| p.parent())
+    }
+
+    /// Returns an iterator over the ancestors of the [`Process`] that are in
+    /// the same [`Session`] as it.
+    ///
+    /// The iteration starts from the parent and stops at the first ancestor
+    /// that belongs to a different [`Session`].
+    pub fn session_ancestors(&self) -> impl Iterator<Item = Arc<Process>> {
+        let session = self.group().session();
+        self.ancestors()
+            .take_while(move |p| Arc::ptr_eq(&p.group().session, &session))
+    }
 }
 
 /// [`ProcessGroup`] & [`Session`]
@@ -70,12 +393,44 @@ impl Process {
         self.group.lock().clone()
     }
 
+    /// The leader of the [`Session`] that the [`Process`] belongs to.
+    ///
+    /// Returns `None` if the session leader has already been freed.
+    pub fn session_leader(&self) -> Option<Arc<Process>> {
+        self.group().session.leader()
+    }
+
+    /// The controlling terminal of the [`Session`] that the [`Process`]
+    /// belongs to, if it exists.
+    pub fn controlling_terminal(&self) -> Option<Arc<dyn Any + Send + Sync>> {
+        self.group().session.terminal()
+    }
+
     fn set_group(self: &Arc<Self>, group: &Arc<ProcessGroup>) {
         let mut self_group = self.group.lock();
+        if !Arc::ptr_eq(&self_group, group) {
+            self.switch_group(&mut self_group, group);
+        }
+    }
 
-        self_group.processes.lock().remove(&self.pid);
+    /// Moves the [`Process`] from `self_group`, the locked current
+    /// [`ProcessGroup`], to a different `group`.
+    ///
+    /// The member maps of both [`ProcessGroup`]s are locked in address order
+    /// to avoid deadlocks with concurrent moves in the opposite direction.
+    /// Both stay locked, along with the [`Process`]'s own group, for the
+    /// whole move, so no reader of either [`ProcessGroup`] can observe the
+    /// [`Process`] in both or neither of them.
+    fn switch_group(
+        self: &Arc<Self>,
+        self_group: &mut Arc<ProcessGroup>,
+        group: &Arc<ProcessGroup>,
+    ) {
+        let old = self_group.clone();
+        let (mut old_processes, mut new_processes) = ProcessGroup::lock_pair(&old, group);
 
-        group.processes.lock().insert(self.pid, self);
+        old_processes.remove(&self.pid);
+        ProcessGroup::insert_locked(&mut new_processes, self);
 
         *self_group = group.clone();
     }
@@ -83,22 +438,27 @@ impl Process {
     /// Creates a new [`Session`] and new [`ProcessGroup`] and moves the
     /// [`Process`] to it.
     ///
-    /// If the [`Process`] is already a session leader, this method does
-    /// nothing and returns `None`.
+    /// If the [`Process`] is already a session leader or a [`ProcessGroup`]
+    /// leader, this method does nothing and returns `None`, as the new
+    /// [`ProcessGroup`] would conflict with the existing one. The same goes
+    /// if the [`Process`] ID is still used by a live [`Session`] or
+    /// [`ProcessGroup`], e.g. one left by a freed [`Process`] with the same
+    /// ID.
     ///
     /// Otherwise, it returns the new [`Session`] and [`ProcessGroup`].
-    ///
-    /// The caller has to ensure that the new [`ProcessGroup`] does not conflict
-    /// with any existing [`ProcessGroup`]. Thus, the [`Process`] must not
-    /// be a [`ProcessGroup`] leader.
-    ///
-    /// Checking [`Session`] conflicts is unnecessary.
     pub fn create_session(self: &Arc<Self>) -> Option<(Arc<Session>, Arc<ProcessGroup>)> {
-        if self.group.lock().session.sid() == self.pid {
+        {
+            let group = self.group.lock();
+            if group.session.sid() == self.pid || group.pgid() == self.pid {
+                return None;
+            }
+        }
+        if table::get_session(self.pid).is_some() || table::get_process_group(self.pid).is_some() {
             return None;
         }
 
         let new_session = Session::new(self.pid);
+        new_session.set_leader(self);
         let new_group = ProcessGroup::new(self.pid, &new_session);
         self.set_group(&new_group);
 
@@ -108,14 +468,13 @@ impl Process {
     /// Creates a new [`ProcessGroup`] and moves the [`Process`] to it.
     ///
     /// If the [`Process`] is already a group leader, this method does nothing
-    /// and returns `None`.
+    /// and returns `None`. The same goes if the [`Process`] ID is still used
+    /// by a live [`ProcessGroup`], e.g. one the [`Process`] has left that
+    /// still has members.
     ///
     /// Otherwise, it returns the new [`ProcessGroup`].
-    ///
-    /// The caller has to ensure that the new [`ProcessGroup`] does not conflict
-    /// with any existing [`ProcessGroup`].
     pub fn create_group(self: &Arc<Self>) -> Option<Arc<ProcessGroup>> {
-        if self.group.lock().pgid() == self.pid {
+        if self.group.lock().pgid() == self.pid || table::get_process_group(self.pid).is_some() {
             return None;
         }
 
@@ -125,6 +484,112 @@ impl Process {
         Some(new_group)
     }
 
+    /// The [`Process`] and its descendants that belong to the given
+    /// [`ProcessGroup`].
+    pub fn subtree_members_of_group(
+        self: &Arc<Self>,
+        group: &Arc<ProcessGroup>,
+    ) -> Vec<Arc<Process>> {
+        let mut members = Vec::new();
+        self.walk_subtree(|p| {
+            if Arc::ptr_eq(&p.group(), group) {
+                members.push(p.clone());
+            }
+        });
+        members
+    }
+
+    /// Returns `true` if the [`Process`] is the only member of its
+    /// [`ProcessGroup`].
+    pub fn is_sole_group_member(&self) -> bool {
+        self.group().len() == 1
+    }
+
+    /// Returns `true` if the [`Process`] is the only member of its
+    /// [`Session`].
+    ///
+    /// Empty [`ProcessGroup`]s that are only kept alive by references, such
+    /// as a kept foreground group, are not counted.
+    pub fn is_sole_session_member(&self) -> bool {
+        self.group().session.process_count() == 1
+    }
+
+    /// Returns the IDs of the [`ProcessGroup`]s in the [`Session`] that would
+    /// become orphaned if the [`Process`] exited.
+    ///
+    /// A [`ProcessGroup`] is orphaned if no member has a parent in a different
+    /// [`ProcessGroup`] of the same [`Session`]. [`ProcessGroup`]s that are
+    /// already orphaned, or that have no member other than the [`Process`],
+    /// are not included.
+    pub fn groups_orphaned_by_exit(&self) -> Vec<Pid> {
+        self.group()
+            .session()
+            .process_groups()
+            .into_iter()
+            .filter(|g| {
+                g.processes().iter().any(|p| !ptr::eq(&**p, self))
+                    && !g.is_orphaned_without(None)
+                    && g.is_orphaned_without(Some(self))
+            })
+            .map(|g| g.pgid())
+            .collect()
+    }
+
+    /// Saves the placement of the [`Process`].
+    pub fn placement(&self) -> Placement {
+        let group = self.group();
+        Placement {
+            pgid: group.pgid(),
+            sid: group.session.sid(),
+        }
+    }
+
+    /// Restores the placement of the [`Process`] from a [`Placement`].
+    ///
+    /// The [`Process`] is moved to `group` if given, or otherwise to the
+    /// [`ProcessGroup`] with the saved ID in `session`, which is created if it
+    /// does not exist. If neither `session` nor `group` is given, the live
+    /// [`Session`] with the saved ID is used, or a new one is created.
+    ///
+    /// Returns the [`ProcessGroup`] the [`Process`] now belongs to, or `None`
+    /// if `session` or `group` is inconsistent with the [`Placement`], or if
+    /// the saved [`ProcessGroup`] ID is still used by a live
+    /// [`ProcessGroup`] of another [`Session`].
+    pub fn restore_placement(
+        self: &Arc<Self>,
+        placement: Placement,
+        session: Option<&Arc<Session>>,
+        group: Option<&Arc<ProcessGroup>>,
+    ) -> Option<Arc<ProcessGroup>> {
+        let session = match (session, group) {
+            (Some(session), _) => session.clone(),
+            (None, Some(group)) => group.session(),
+            (None, None) => {
+                table::get_session(placement.sid).unwrap_or_else(|| Session::new(placement.sid))
+            }
+        };
+        if session.sid() != placement.sid {
+            return None;
+        }
+
+        let group = match group {
+            Some(group) => group.clone(),
+            None => match table::get_process_group(placement.pgid) {
+                Some(existing) => existing,
+                None => ProcessGroup::new(placement.pgid, &session),
+            },
+        };
+        if group.pgid() != placement.pgid || !Arc::ptr_eq(&group.session, &session) {
+            return None;
+        }
+
+        if self.pid == placement.sid {
+            session.set_leader(self);
+        }
+        self.set_group(&group);
+        Some(group)
+    }
+
     /// Moves the [`Process`] to a specified [`ProcessGroup`].
     ///
     /// Returns `true` if the move succeeded. The move failed if the
@@ -133,15 +598,16 @@ impl Process {
     /// If the [`Process`] is already in the specified [`ProcessGroup`], this
     /// method does nothing and returns `true`.
     pub fn move_to_group(self: &Arc<Self>, group: &Arc<ProcessGroup>) -> bool {
-        if Arc::ptr_eq(&self.group.lock(), group) {
+        let mut self_group = self.group.lock();
+        if Arc::ptr_eq(&self_group, group) {
             return true;
         }
 
-        if !Arc::ptr_eq(&self.group.lock().session, &group.session) {
+        if !self_group.same_session(group) {
             return false;
         }
 
-        self.set_group(group);
+        self.switch_group(&mut self_group, group);
         true
     }
 }
@@ -156,14 +622,72 @@ impl Process {
     /// Removes a thread from this [`Process`] and sets the exit code if the
     /// group has not exited.
     ///
-    /// Returns `true` if this was the last thread in the process.
+    /// If this was the last thread in the process, the [`Process`] exits as
+    /// with [`Process::exit`] and `true` is returned.
     pub fn exit_thread(self: &Arc<Self>, tid: Pid, exit_code: i32) -> bool {
+        let last = {
+            let mut tg = self.tg.lock();
+            if !self.group_exited.load(Ordering::Acquire) {
+                self.exit_code.store(exit_code, Ordering::Release);
+            }
+            if tid == self.pid {
+                tg.leader_exited = true;
+            }
+            tg.threads.remove(&tid);
+            tg.threads.is_empty()
+        };
+        if last {
+            self.exit();
+        }
+        last
+    }
+
+    /// Returns `true` if `tid` is the group leader thread of this
+    /// [`Process`], i.e. its thread ID is the [`Process`] ID.
+    pub fn is_group_leader(&self, tid: Pid) -> bool {
+        tid == self.pid
+    }
+
+    /// The thread ID of the group leader thread of this [`Process`], if it
+    /// is still alive.
+    pub fn group_leader(&self) -> Option<Pid> {
+        self.tg
+            .lock()
+            .threads
+            .contains(&self.pid)
+            .then_some(self.pid)
+    }
+
+    /// Returns `true` if the group leader thread, whose thread ID is the
+    /// [`Process`] ID, has exited while other threads are still running.
+    pub fn has_zombie_leader(&self) -> bool {
+        let tg = self.tg.lock();
+        tg.leader_exited && !tg.threads.is_empty()
+    }
+
+    /// Collapses the threads of this [`Process`] into a single one, as done
+    /// by `execve`.
+    ///
+    /// The surviving thread becomes the group leader, i.e. its thread ID
+    /// becomes the [`Process`] ID. Returns the other threads, which the
+    /// caller has to destroy, as [`CollapsedThreads`].
+    ///
+    /// Returns `None` and changes nothing if `surviving_tid` is not a thread
+    /// of this [`Process`].
+    pub fn collapse_to_thread(&self, surviving_tid: Pid) -> Option<CollapsedThreads> {
         let mut tg = self.tg.lock();
-        if !tg.group_exited {
-            tg.exit_code = exit_code;
+        if !tg.threads.contains(&surviving_tid) {
+            return None;
         }
-        tg.threads.remove(&tid);
-        tg.threads.is_empty()
+        let mut threads = core::mem::take(&mut tg.threads);
+        threads.remove(&surviving_tid);
+        let leader_alive = threads.remove(&self.pid);
+        tg.threads.insert(self.pid);
+        tg.leader_exited = false;
+        Some(CollapsedThreads {
+            threads: threads.into_iter().collect(),
+            leader_alive,
+        })
     }
 
     /// Get all threads in this [`Process`].
@@ -171,19 +695,49 @@ impl Process {
         self.tg.lock().threads.iter().cloned().collect()
     }
 
+    /// The number of threads in this [`Process`].
+    pub fn thread_count(&self) -> usize {
+        self.tg.lock().threads.len()
+    }
+
     /// Returns `true` if the [`Process`] is group exited.
     pub fn is_group_exited(&self) -> bool {
-        self.tg.lock().group_exited
+        self.group_exited.load(Ordering::Acquire)
     }
 
-    /// Marks the [`Process`] as group exited.
-    pub fn group_exit(&self) {
-        self.tg.lock().group_exited = true;
+    /// Marks the [`Process`] as group exited with the given exit code, as
+    /// done by `exit_group`.
+    ///
+    /// The exit code is kept if the [`Process`] has already group exited, and
+    /// is not overwritten by exiting threads afterwards.
+    ///
+    /// Returns the threads that are still alive, which the caller has to
+    /// terminate.
+    pub fn group_exit(&self, exit_code: i32) -> Vec<Pid> {
+        let tg = self.tg.lock();
+        if !self.group_exited.load(Ordering::Acquire) {
+            self.exit_code.store(exit_code, Ordering::Release);
+            self.group_exited.store(true, Ordering::Release);
+        }
+        tg.threads.iter().cloned().collect()
     }
 
     /// The exit code of the [`Process`].
     pub fn exit_code(&self) -> i32 {
-        self.tg.lock().exit_code
+        self.exit_code.load(Ordering::Acquire)
+    }
+
+    /// The [`WaitStatus`] of a zombie [`Process`], or `None` if it has not
+    /// exited yet.
+    ///
+    /// This is the status recorded by [`Process::terminate`], or
+    /// [`WaitStatus::Exited`] with the exit code otherwise.
+    pub fn wait_status(&self) -> Option<WaitStatus> {
+        if !self.is_zombie() {
+            return None;
+        }
+        let status = *self.exit_status.lock();
+        Some(status.unwrap_or(WaitStatus::Exited(self.exit_code() as u8)))
     }
 }
 
@@ -191,44 +745,360 @@ impl Process {
 impl Process {
     /// Returns `true` if the [`Process`] is a zombie process.
     pub fn is_zombie(&self) -> bool {
-        self.is_zombie.load(Ordering::Acquire)
+        self.state() == ProcessState::Zombie
+    }
+
+    /// The [`ProcessState`] of the [`Process`].
+    pub fn state(&self) -> ProcessState {
+        ProcessState::from_raw(self.state.load(Ordering::Acquire))
+    }
+
+    /// Marks a running [`Process`] as stopped.
+    ///
+    /// Returns `false` if the [`Process`] was not running.
+    pub fn set_stopped(&self) -> bool {
+        self.transition(ProcessState::Running, ProcessState::Stopped)
+    }
+
+    /// Marks a stopped [`Process`] as running again.
+    ///
+    /// Returns `false` if the [`Process`] was not stopped.
+    pub fn set_running(&self) -> bool {
+        self.transition(ProcessState::Stopped, ProcessState::Running)
+    }
+
+    fn transition(&self, from: ProcessState, to: ProcessState) -> bool {
+        self.state
+            .compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire)
+            .is_ok()
+    }
+
+    /// The [`Process`] that would inherit the child processes if the
+    /// [`Process`] exited, i.e. the init process or the nearest subreaper.
+    ///
+    /// Returns `None` if the [`Process`] is the init process, or if the init
+    /// process has not been created yet.
+    pub fn reaper(self: &Arc<Self>) -> Option<Arc<Process>> {
+        let init = table::init_process_ref()?;
+        if Arc::ptr_eq(self, init) {
+            return None;
+        }
+        if CHILD_SUBREAPERS.load(Ordering::Acquire) == 0 {
+            return Some(init.clone());
+        }
+
+        let subreaper = self
+            .ancestors()
+            .find(|p| p.is_child_subreaper() && !p.is_zombie());
+        Some(subreaper.unwrap_or_else(|| init.clone()))
+    }
+
+    /// Returns `true` if the [`Process`] is a child subreaper.
+    pub fn is_child_subreaper(&self) -> bool {
+        self.is_child_subreaper.load(Ordering::Acquire)
+    }
+
+    /// Sets whether the [`Process`] is a child subreaper.
+    ///
+    /// Orphaned descendants of a child subreaper are inherited by it instead
+    /// of by the init process, as with Linux's `PR_SET_CHILD_SUBREAPER`.
+    ///
+    /// A zombie [`Process`] is no longer a child subreaper.
+    pub fn set_child_subreaper(&self, child_subreaper: bool) {
+        if self.is_zombie() {
+            return;
+        }
+        self.update_child_subreaper(child_subreaper);
+    }
+
+    fn update_child_subreaper(&self, child_subreaper: bool) {
+        let old = self
+            .is_child_subreaper
+            .swap(child_subreaper, Ordering::AcqRel);
+        match (old, child_subreaper) {
+            (false, true) => CHILD_SUBREAPERS.fetch_add(1, Ordering::AcqRel),
+            (true, false) => CHILD_SUBREAPERS.fetch_sub(1, Ordering::AcqRel),
+            _ => 0,
+        };
+    }
+
+    /// Terminates the [`Process`] as described by `status`, marking it as a
+    /// zombie process as done by [`Process::exit`].
+    ///
+    /// `status` is reported by [`Process::wait_status`], so that a
+    /// [`Process`] killed by a signal can be told apart from one that exited
+    /// normally. It must be [`WaitStatus::Exited`] or
+    /// [`WaitStatus::Signaled`].
+    ///
+    /// As with [`Process::group_exit`], the exit code is kept if the
+    /// [`Process`] has already group exited. Terminating a zombie
+    /// [`Process`] changes nothing.
+    pub fn terminate(self: &Arc<Self>, status: WaitStatus) {
+        debug_assert!(
+            matches!(status, WaitStatus::Exited(_) | WaitStatus::Signaled { .. }),
+            "invalid termination status {status:?}"
+        );
+        {
+            let _tg = self.tg.lock();
+            let group_exited = self.group_exited.load(Ordering::Acquire);
+            match status {
+                _ if self.is_zombie() => {}
+                // The exit code of the group exit is reported instead.
+                WaitStatus::Exited(_) if group_exited => {}
+                WaitStatus::Exited(code) => {
+                    self.exit_code.store(code as i32, Ordering::Release);
+                    self.exit_status.lock().get_or_insert(status);
+                }
+                _ => {
+                    self.exit_status.lock().get_or_insert(status);
+                }
+            }
+        }
+        self.exit();
     }
 
     /// Terminates the [`Process`], marking it as a zombie process.
     ///
     /// Child processes are inherited by the init process or by the nearest
-    /// subreaper process.
+    /// subreaper process. If the [`Process`] is a session leader, its
+    /// [`Session`] loses its terminal and its foreground [`ProcessGroup`] is
+    /// passed to the [`on_hangup`](crate::on_hangup) hook.
     ///
-    /// This method panics if the [`Process`] is the init process.
+    /// Exiting a zombie [`Process`] again does nothing, so the children are
+    /// only reparented and the hooks only invoked once.
+    ///
+    /// If the init process has not been created yet, as for a detached kernel
+    /// [`Process`] exiting early, the children stay with the zombie.
+    ///
+    /// The exit code is left as set by the exiting threads. Prefer
+    /// [`Process::terminate`], which also records how the [`Process`] died.
+    /// [`Process::exit_thread`] calls this method when the last thread exits.
+    ///
+    /// The init process never exits, so this method does nothing for it.
     pub fn exit(self: &Arc<Self>) {
-        // TODO: child subreaper
-        let reaper = INIT_PROC.get().unwrap();
-
-        if Arc::ptr_eq(self, reaper) {
+        if self.is_init() {
             return;
         }
 
-        let mut children = self.children.lock(); // Acquire the lock first
-        self.is_zombie.store(true, Ordering::Release);
+        let reparented = {
+            let mut children = self.children.lock(); // Acquire the lock first
+            let old = self
+                .state
+                .swap(ProcessState::Zombie as u8, Ordering::AcqRel);
+            if old == ProcessState::Zombie as u8 {
+                return;
+            }
+            self.update_child_subreaper(false);
+
+            self.reparent_children(&mut children)
+        };
+        if let Some((reaper, reparented)) = &reparented {
+            for child in reparented {
+                hooks::reparented(reaper, child);
+            }
+        }
+
+        for group in self.group().session.on_leader_exit(self) {
+            hooks::hangup(&group);
+        }
+
+        if let Some(parent) = self.parent() {
+            hooks::child_exited(&parent, self);
+        }
+    }
 
-        let mut reaper_children = reaper.children.lock();
-        let reaper = Arc::downgrade(reaper);
+    /// Hands the locked `children` of the [`Process`] over to its reaper.
+    ///
+    /// Returns the reaper and the reparented children, or `None` if there is
+    /// no reaper.
+    fn reparent_children(
+        self: &Arc<Self>,
+        children: &mut StrongMap<Pid, Arc<Process>>,
+    ) -> Option<(Arc<Process>, Vec<Arc<Process>>)> {
+        loop {
+            let reaper = self.reaper()?;
+            let mut reaper_children = reaper.children.lock();
+            // A subreaper becomes a zombie with its children locked and hands
+            // them over right away, so one found to be a zombie here may have
+            // exited after it was picked and would never hand these over.
+            if reaper.is_zombie() {
+                continue;
+            }
 
-        for (pid, child) in core::mem::take(&mut *children) {
-            *child.parent.lock() = reaper.clone();
-            reaper_children.insert(pid, child);
+            let weak_reaper = Arc::downgrade(&reaper);
+            let reparented = core::mem::take(children)
+                .into_iter()
+                .map(|(pid, child)| {
+                    *child.parent.lock() = weak_reaper.clone();
+                    reaper_children.insert(pid, child.clone());
+                    child
+                })
+                .collect();
+            drop(reaper_children);
+            return Some((reaper, reparented));
         }
     }
 
-    /// Frees a zombie [`Process`]. Removes it from the parent.
+    /// Frees a zombie [`Process`]. Removes it from the parent and the
+    /// [`process_table`](crate::process_table).
     ///
-    /// This method panics if the [`Process`] is not a zombie.
+    /// This method panics if the [`Process`] is not a zombie, or has already
+    /// been freed.
     pub fn free(&self) {
-        assert!(self.is_zombie(), "only zombie process can be freed");
+        if let Err(err) = self.try_free() {
+            panic!("{err}");
+        }
+    }
+
+    /// Frees a zombie [`Process`] as done by [`Process::free`], returning an
+    /// error instead of panicking if the [`Process`] is not a zombie.
+    ///
+    /// If several callers race to free the same [`Process`], as with two
+    /// concurrent `wait`s, only one of them succeeds and the others get an
+    /// error, so the [`Process`] is only reported once.
+    pub fn try_free(&self) -> Result<(), NotZombie> {
+        if !self.is_zombie() {
+            return Err(NotZombie);
+        }
 
         if let Some(parent) = self.parent() {
-            parent.children.lock().remove(&self.pid);
+            let mut children = parent.children.lock();
+            if !children.get(&self.pid).is_some_and(|c| ptr::eq(&**c, self)) {
+                return Err(NotZombie);
+            }
+            let removed = children.remove(&self.pid);
+            drop(children);
+            drop(removed);
+        }
+        if !table::remove_process(self) {
+            return Err(NotZombie);
+        }
+        Ok(())
+    }
+
+    /// Frees up to `max` zombie child [`Process`]es, as done by
+    /// [`free`](Process::free).
+    ///
+    /// Returns the IDs and exit codes of the freed children.
+    pub fn reap_available(&self, max: usize) -> Vec<(Pid, i32)> {
+        let reaped: Vec<Arc<Process>> = {
+            let mut children = self.children.lock();
+            let zombies: Vec<Pid> = children
+                .iter()
+                .filter(|(_, child)| child.is_zombie())
+                .map(|(pid, _)| *pid)
+                .take(max)
+                .collect();
+            zombies
+                .into_iter()
+                .filter_map(|pid| children.remove(&pid))
+                .collect()
+        };
+        reaped
+            .iter()
+            .map(|child| {
+                table::remove_process(child);
+                (child.pid, child.exit_code())
+            })
+            .collect()
+    }
+
+    /// Terminates the [`Process`] with the given exit code and frees it right
+    /// away.
+    ///
+    /// This bypasses the parent waiting for the [`Process`], so it must only be
+    /// used when the parent is known not to wait for it.
+    ///
+    /// This method panics if the [`Process`] is the init process.
+    pub fn terminate_and_reap(self: &Arc<Self>, exit_code: i32) {
+        {
+            let _tg = self.tg.lock();
+            if !self.group_exited.load(Ordering::Acquire) {
+                self.exit_code.store(exit_code, Ordering::Release);
+            }
+        }
+        self.exit();
+        self.free();
+    }
+}
+
+/// Login ID
+impl Process {
+    /// The audit login user ID of the [`Process`], if it has been set.
+    ///
+    /// The login ID is inherited by child processes.
+    pub fn loginuid(&self) -> Option<u32> {
+        *self.loginuid.lock()
+    }
+
+    /// Sets the audit login user ID of the [`Process`].
+    ///
+    /// The login ID can only be set once, unless `privileged` is `true`.
+    /// Returns `false` if it has already been set and the caller is not
+    /// privileged.
+    pub fn set_loginuid(&self, uid: u32, privileged: bool) -> bool {
+        let mut loginuid = self.loginuid.lock();
+        if loginuid.is_some() && !privileged {
+            return false;
         }
+        *loginuid = Some(uid);
+        true
+    }
+}
+
+/// Credentials
+impl Process {
+    /// The [`Credentials`] of the [`Process`].
+    ///
+    /// The credentials are inherited by child processes. The init process
+    /// starts with all IDs set to zero.
+    pub fn credentials(&self) -> Credentials {
+        *self.credentials.lock()
+    }
+
+    /// Sets the [`Credentials`] of the [`Process`].
+    pub fn set_credentials(&self, credentials: Credentials) {
+        *self.credentials.lock() = credentials;
+    }
+}
+
+/// Associated data
+impl Process {
+    /// Associates `data` with this [`Process`].
+    ///
+    /// Data of different types are stored independently, each behind its own
+    /// lock. Returns the previous data of type `T`, if any.
+    pub fn insert_data<T: Any + Send + Sync>(&self, data: T) -> Option<ProcessData<T>> {
+        let slot = Arc::new(SpinNoIrq::new(data));
+        let old = self.data.lock().insert(TypeId::of::<T>(), slot)?;
+        old.downcast().ok().map(ProcessData)
+    }
+
+    /// Gets the data of type `T` associated with this [`Process`], if any.
+    ///
+    /// All holders of the returned [`ProcessData`] share the same value, and
+    /// see changes made through [`Process::with_data_mut`].
+    pub fn data<T: Any + Send + Sync>(&self) -> Option<ProcessData<T>> {
+        let data = self.data.lock().get(&TypeId::of::<T>())?.clone();
+        data.downcast().ok().map(ProcessData)
+    }
+
+    /// Removes the data of type `T` associated with this [`Process`], and
+    /// returns it.
+    pub fn remove_data<T: Any + Send + Sync>(&self) -> Option<ProcessData<T>> {
+        let data = self.data.lock().remove(&TypeId::of::<T>())?;
+        data.downcast().ok().map(ProcessData)
+    }
+
+    /// Calls `f` with mutable access to the data of type `T` associated with
+    /// this [`Process`], and returns its result.
+    ///
+    /// Only the data of type `T` is locked while `f` is called, so `f` may
+    /// access the data of other types, but must not access the data of type
+    /// `T` of this [`Process`] again. Returns `None` if there is no data of
+    /// type `T`.
+    pub fn with_data_mut<T: Any + Send + Sync, R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+        Some(self.data::<T>()?.with_mut(f))
     }
 }
 
@@ -237,12 +1107,11 @@ impl fmt::Debug for Process {
         let mut builder = f.debug_struct("Process");
         builder.field("pid", &self.pid);
 
-        let tg = self.tg.lock();
-        if tg.group_exited {
-            builder.field("group_exited", &tg.group_exited);
+        if self.is_group_exited() {
+            builder.field("group_exited", &true);
         }
         if self.is_zombie() {
-            builder.field("exit_code", &tg.exit_code);
+            builder.field("exit_code", &self.exit_code());
         }
 
         if let Some(parent) = self.parent() {
@@ -253,56 +1122,121 @@ impl fmt::Debug for Process {
     }
 }
 
+impl Drop for Process {
+    fn drop(&mut self) {
+        self.update_child_subreaper(false);
+    }
+}
+
 /// Builder
 impl Process {
-    fn new(pid: Pid, parent: Option<Arc<Process>>) -> Arc<Process> {
-        let group = parent.as_ref().map_or_else(
-            || {
-                let session = Session::new(pid);
-                ProcessGroup::new(pid, &session)
-            },
-            |p| p.group(),
-        );
-
-        let process = Arc::new(Process {
+    fn new(pid: Pid, parent: Option<Arc<Process>>, group: Arc<ProcessGroup>) -> Arc<Process> {
+        Arc::new(Process {
             pid,
-            is_zombie: AtomicBool::new(false),
+            start_seq: START_SEQ.fetch_add(1, Ordering::Relaxed),
+            state: AtomicU8::new(ProcessState::Running as u8),
+            is_child_subreaper: AtomicBool::new(false),
             tg: SpinNoIrq::new(ThreadGroup::default()),
+            exit_code: AtomicI32::new(0),
+            group_exited: AtomicBool::new(false),
+            exit_status: SpinNoIrq::new(None),
             children: SpinNoIrq::new(StrongMap::new()),
             parent: SpinNoIrq::new(parent.as_ref().map(Arc::downgrade).unwrap_or_default()),
-            group: SpinNoIrq::new(group.clone()),
-        });
+            group: SpinNoIrq::new(group),
+            loginuid: SpinNoIrq::new(parent.as_ref().and_then(|p| p.loginuid())),
+            credentials: SpinNoIrq::new(
+                parent.as_ref().map(|p| p.credentials()).unwrap_or_default(),
+            ),
+            data: SpinNoIrq::new(BTreeMap::new()),
+        })
+    }
 
-        group.processes.lock().insert(pid, &process);
+    /// Links a [`Process`] created by [`Process::new`] into its
+    /// [`ProcessGroup`] and its parent. It must already be in the process
+    /// table.
+    fn link(process: &Arc<Process>) {
+        let group = process.group();
+        group.insert_process(process);
 
-        if let Some(parent) = parent {
-            parent.children.lock().insert(pid, process.clone());
+        if let Some(parent) = process.parent() {
+            parent.children.lock().insert(process.pid, process.clone());
         } else {
-            INIT_PROC.init_once(process.clone());
+            assert!(
+                table::set_init_process(process.clone()),
+                "init process already registered"
+            );
+            group.session.set_leader(process);
         }
-
-        process
     }
 
     /// Creates a init [`Process`].
     ///
-    /// This function can be called multiple times, but
-    /// [`ProcessBuilder::build`] on the the result must be called only once.
+    /// The init [`Process`] can only be created once. This function panics if
+    /// it has already been created.
     pub fn new_init(pid: Pid) -> Arc<Process> {
-        Self::new(pid, None)
+        let session = Session::new(pid);
+        let group = ProcessGroup::new(pid, &session);
+        let process = Self::new(pid, None, group);
+        table::insert_process(&process);
+        Self::link(&process);
+        process
+    }
+
+    /// Creates a detached [`Process`] for a kernel task.
+    ///
+    /// The [`Process`] has no parent and belongs to a dedicated kernel
+    /// [`ProcessGroup`] and [`Session`], shared by all detached
+    /// [`Process`]es. Neither is registered in the global tables, so they
+    /// never show up as a user [`ProcessGroup`] or [`Session`]. The
+    /// [`Process`] itself is still registered in the process table, so its
+    /// [`Pid`] stays in use.
+    ///
+    /// A detached [`Process`] can be created and can exit before the init
+    /// process exists. It then has no [`reaper`](Process::reaper).
+    pub fn new_detached(pid: Pid) -> Arc<Process> {
+        let group = ProcessGroup::kernel().clone();
+        let process = Self::new(pid, None, group.clone());
+        table::insert_process(&process);
+        group.insert_process(&process);
+        process
+    }
+
+    /// Returns `true` if the [`Process`] was created by
+    /// [`Process::new_detached`] and has not moved to a user
+    /// [`ProcessGroup`] since.
+    pub fn is_detached(&self) -> bool {
+        Arc::ptr_eq(&self.group(), ProcessGroup::kernel())
     }
 
     /// Creates a child [`Process`].
+    ///
+    /// The caller guarantees that `pid` is not used by any other [`Process`]
+    /// in the process table. See [`Process::try_fork`] for a checked variant.
     pub fn fork(self: &Arc<Process>, pid: Pid) -> Arc<Process> {
-        Self::new(pid, Some(self.clone()))
+        let process = Self::new(pid, Some(self.clone()), self.group());
+        table::insert_process(&process);
+        Self::link(&process);
+        process
     }
-}
 
-static INIT_PROC: LazyInit<Arc<Process>> = LazyInit::new();
+    /// Creates a child [`Process`], failing if `pid` is already used by a
+    /// [`Process`] in the process table.
+    ///
+    /// Zombie [`Process`]es keep their [`Pid`] in use until they are freed.
+    /// The check and the insertion into the process table happen under the
+    /// same lock, so concurrent calls with the same [`Pid`] cannot both
+    /// succeed.
+    pub fn try_fork(self: &Arc<Process>, pid: Pid) -> Result<Arc<Process>, PidInUse> {
+        let process = Self::new(pid, Some(self.clone()), self.group());
+        table::try_insert_process(&process)?;
+        Self::link(&process);
+        Ok(process)
+    }
+}
 
 /// Gets the init process.
 ///
 /// This function panics if the init process has not been initialized yet.
 pub fn init_proc() -> Arc<Process> {
-    INIT_PROC.get().unwrap().clone()
+    table::init_process().unwrap()
 }
@@ -1,32 +1,130 @@
 use alloc::{
+    boxed::Box,
     sync::{Arc, Weak},
     vec::Vec,
 };
-use core::fmt;
+use core::{
+    any::Any,
+    fmt, ptr,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
-use kspin::SpinNoIrq;
+use kspin::{SpinNoIrq, SpinNoIrqGuard};
+use lazyinit::LazyInit;
 use weak_map::WeakMap;
 
-use crate::{Pid, Process, Session};
+use crate::{Pid, Process, Session, table};
+
+type MembersGuard<'a> = SpinNoIrqGuard<'a, WeakMap<Pid, Weak<Process>>>;
 
 /// A [`ProcessGroup`] is a collection of [`Process`]es.
 pub struct ProcessGroup {
     pgid: Pid,
+    generation: u64,
     pub(crate) session: Arc<Session>,
     pub(crate) processes: SpinNoIrq<WeakMap<Pid, Weak<Process>>>,
+    data: LazyInit<Box<dyn Any + Send + Sync>>,
 }
 
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// The ID of the kernel [`ProcessGroup`] and [`Session`].
+const KERNEL_ID: Pid = 0;
+
+static KERNEL_GROUP: LazyInit<Arc<ProcessGroup>> = LazyInit::new();
+
 impl ProcessGroup {
     /// Create a new [`ProcessGroup`] within a [`Session`].
     pub(crate) fn new(pgid: Pid, session: &Arc<Session>) -> Arc<Self> {
+        let group = Self::new_unlisted(pgid, session);
+        table::insert_process_group(&group);
+        group
+    }
+
+    /// Create a new [`ProcessGroup`] within a [`Session`] that is not
+    /// registered in the [`process_group_table`](crate::process_group_table).
+    fn new_unlisted(pgid: Pid, session: &Arc<Session>) -> Arc<Self> {
         let group = Arc::new(Self {
             pgid,
+            generation: GENERATION.fetch_add(1, Ordering::Relaxed),
             session: session.clone(),
             processes: SpinNoIrq::new(WeakMap::new()),
+            data: LazyInit::new(),
         });
         session.process_groups.lock().insert(pgid, &group);
         group
     }
+
+    /// The [`ProcessGroup`] of detached kernel [`Process`]es, created by
+    /// [`Process::new_detached`].
+    ///
+    /// It is the only [`ProcessGroup`] of its own [`Session`], and neither is
+    /// registered in the global tables, so they are never found by ID.
+    pub(crate) fn kernel() -> &'static Arc<ProcessGroup> {
+        KERNEL_GROUP.call_once(|| {
+            let session = Session::new_unlisted(KERNEL_ID);
+            Self::new_unlisted(KERNEL_ID, &session)
+        });
+        KERNEL_GROUP.get().unwrap()
+    }
+
+    /// Adds a [`Process`] to the [`ProcessGroup`].
+    pub(crate) fn insert_process(&self, process: &Arc<Process>) {
+        Self::insert_locked(&mut self.processes.lock(), process);
+    }
+
+    /// Locks the member maps of two different [`ProcessGroup`]s in address
+    /// order, so that concurrent callers with the groups swapped cannot
+    /// deadlock. The guards are returned in argument order.
+    pub(crate) fn lock_pair<'a>(
+        a: &'a ProcessGroup,
+        b: &'a ProcessGroup,
+    ) -> (MembersGuard<'a>, MembersGuard<'a>) {
+        debug_assert!(!ptr::eq(a, b), "cannot lock a group twice");
+        if ptr::from_ref(a) < ptr::from_ref(b) {
+            let a = a.processes.lock();
+            (a, b.processes.lock())
+        } else {
+            let b = b.processes.lock();
+            (a.processes.lock(), b)
+        }
+    }
+
+    /// Adds a [`Process`] to the locked member map of a [`ProcessGroup`].
+    pub(crate) fn insert_locked(
+        processes: &mut WeakMap<Pid, Weak<Process>>,
+        process: &Arc<Process>,
+    ) {
+        debug_assert!(
+            processes
+                .get(&process.pid())
+                .is_none_or(|p| Arc::ptr_eq(&p, process)),
+            "pid {} is already used by another live process in the group",
+            process.pid()
+        );
+        processes.insert(process.pid(), process);
+    }
+
+    /// Returns `true` if the [`ProcessGroup`] is orphaned, pretending that
+    /// `excluded` has exited and its children have been reparented.
+    ///
+    /// A [`ProcessGroup`] is orphaned if no member has a parent in a different
+    /// [`ProcessGroup`] of the same [`Session`].
+    pub(crate) fn is_orphaned_without(&self, excluded: Option<&Process>) -> bool {
+        let is_excluded = |p: &Process| excluded.is_some_and(|e| ptr::eq(p, e));
+        !self.processes().iter().any(|p| {
+            if is_excluded(p) {
+                return false;
+            }
+            let parent = match p.parent() {
+                Some(parent) if is_excluded(&parent) => parent.reaper().unwrap_or(parent),
+                Some(parent) => parent,
+                None => return false,
+            };
+            let group = parent.group();
+            !ptr::eq(&*group, self) && Arc::ptr_eq(&group.session, &self.session)
+        })
+    }
 }
 
 impl ProcessGroup {
@@ -35,15 +133,127 @@ impl ProcessGroup {
         self.pgid
     }
 
+    /// The generation of the [`ProcessGroup`].
+    ///
+    /// Unlike the [`ProcessGroup`] ID, which may be reused, the generation is
+    /// unique among all [`ProcessGroup`]s ever created.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
     /// The [`Session`] that the [`ProcessGroup`] belongs to.
     pub fn session(&self) -> Arc<Session> {
         self.session.clone()
     }
 
+    /// Returns `true` if the [`ProcessGroup`] is in the same [`Session`] as
+    /// `other`.
+    pub fn same_session(&self, other: &Arc<ProcessGroup>) -> bool {
+        Arc::ptr_eq(&self.session, &other.session)
+    }
+
+    /// The leader [`Process`] of this [`ProcessGroup`], i.e. the member whose
+    /// ID is the [`ProcessGroup`] ID.
+    ///
+    /// Returns `None` if the leader has left the [`ProcessGroup`] or has
+    /// already been freed.
+    pub fn leader(&self) -> Option<Arc<Process>> {
+        self.processes.lock().get(&self.pgid)
+    }
+
     /// The [`Process`]es that belong to this [`ProcessGroup`].
     pub fn processes(&self) -> Vec<Arc<Process>> {
         self.processes.lock().values().collect()
     }
+
+    /// Calls `f` on every live [`Process`] in this [`ProcessGroup`].
+    ///
+    /// The members are snapshotted first, so `f` is called without the
+    /// [`ProcessGroup`]'s lock held and is free to lock the [`Process`].
+    pub fn for_each_process<F: FnMut(&Arc<Process>)>(&self, f: F) {
+        self.processes().iter().for_each(f);
+    }
+
+    /// Returns `true` if the [`ProcessGroup`] is orphaned.
+    ///
+    /// A [`ProcessGroup`] is orphaned if no member has a parent in a different
+    /// [`ProcessGroup`] of the same [`Session`].
+    pub fn is_orphaned(&self) -> bool {
+        self.is_orphaned_without(None)
+    }
+
+    /// Stops all running [`Process`]es in this [`ProcessGroup`].
+    ///
+    /// Returns the number of [`Process`]es that were stopped.
+    pub fn stop_all(&self) -> usize {
+        self.processes().iter().filter(|p| p.set_stopped()).count()
+    }
+
+    /// Continues all stopped [`Process`]es in this [`ProcessGroup`].
+    ///
+    /// Returns the number of [`Process`]es that were continued.
+    pub fn continue_all(&self) -> usize {
+        self.processes().iter().filter(|p| p.set_running()).count()
+    }
+
+    /// The number of live [`Process`]es that belong to this
+    /// [`ProcessGroup`].
+    pub fn len(&self) -> usize {
+        self.processes.lock().len()
+    }
+
+    /// The numbers of live [`Process`]es in this and another
+    /// [`ProcessGroup`], counted together.
+    ///
+    /// Both member maps are locked at once, in the same order as a move
+    /// between the two, so a [`Process`] moving between them is counted
+    /// exactly once.
+    ///
+    /// This is not part of the public API. It only exists so that tests can
+    /// check that moves are atomic.
+    #[doc(hidden)]
+    pub fn len_pair(&self, other: &ProcessGroup) -> (usize, usize) {
+        if ptr::eq(self, other) {
+            let len = self.len();
+            return (len, len);
+        }
+        let (this, other) = Self::lock_pair(self, other);
+        (this.len(), other.len())
+    }
+
+    /// Returns `true` if no live [`Process`] belongs to this
+    /// [`ProcessGroup`].
+    pub fn is_empty(&self) -> bool {
+        self.processes.lock().is_empty()
+    }
+
+    /// The threads of all [`Process`]es that belong to this
+    /// [`ProcessGroup`].
+    pub fn threads(&self) -> Vec<Pid> {
+        self.processes().iter().flat_map(|p| p.threads()).collect()
+    }
+}
+
+/// Associated data
+impl ProcessGroup {
+    /// Sets the data associated with this [`ProcessGroup`].
+    ///
+    /// The data can only be set once. Returns `false` if it was already set.
+    pub fn set_data<T: Any + Send + Sync>(&self, data: T) -> bool {
+        self.data.call_once(|| Box::new(data)).is_some()
+    }
+
+    /// Gets the data associated with this [`ProcessGroup`], if it is set and
+    /// of type `T`.
+    pub fn data<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.data.get()?.downcast_ref()
+    }
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        table::prune_process_group(self.pgid);
+    }
 }
 
 impl fmt::Debug for ProcessGroup {
@@ -1,28 +1,52 @@
 use alloc::{
+    boxed::Box,
     sync::{Arc, Weak},
     vec::Vec,
 };
-use core::{any::Any, fmt};
+use core::{
+    any::Any,
+    fmt, ptr,
+    sync::atomic::{AtomicU64, Ordering},
+};
 
 use kspin::SpinNoIrq;
+use lazyinit::LazyInit;
 use weak_map::WeakMap;
 
-use crate::{Pid, ProcessGroup};
+use crate::{Pid, Process, ProcessGroup, table};
 
 /// A [`Session`] is a collection of [`ProcessGroup`]s.
 pub struct Session {
     sid: Pid,
+    generation: u64,
     pub(crate) process_groups: SpinNoIrq<WeakMap<Pid, Weak<ProcessGroup>>>,
+    leader: SpinNoIrq<Weak<Process>>,
     terminal: SpinNoIrq<Option<Arc<dyn Any + Send + Sync>>>,
+    foreground: SpinNoIrq<Weak<ProcessGroup>>,
+    data: LazyInit<Box<dyn Any + Send + Sync>>,
 }
 
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
 impl Session {
     /// Create a new [`Session`].
     pub(crate) fn new(sid: Pid) -> Arc<Self> {
+        let session = Self::new_unlisted(sid);
+        table::insert_session(&session);
+        session
+    }
+
+    /// Create a new [`Session`] that is not registered in the
+    /// [`session_table`](crate::session_table).
+    pub(crate) fn new_unlisted(sid: Pid) -> Arc<Self> {
         Arc::new(Self {
             sid,
+            generation: GENERATION.fetch_add(1, Ordering::Relaxed),
             process_groups: SpinNoIrq::new(WeakMap::new()),
+            leader: SpinNoIrq::new(Weak::new()),
             terminal: SpinNoIrq::new(None),
+            foreground: SpinNoIrq::new(Weak::new()),
+            data: LazyInit::new(),
         })
     }
 }
@@ -33,11 +57,57 @@ impl Session {
         self.sid
     }
 
+    /// The generation of the [`Session`].
+    ///
+    /// Unlike the [`Session`] ID, which may be reused, the generation is
+    /// unique among all [`Session`]s ever created.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// The leader [`Process`] of this [`Session`], i.e. the one that created
+    /// it.
+    ///
+    /// Returns `None` if the session leader has already been freed.
+    pub fn leader(&self) -> Option<Arc<Process>> {
+        self.leader.lock().upgrade()
+    }
+
+    pub(crate) fn set_leader(&self, leader: &Arc<Process>) {
+        *self.leader.lock() = Arc::downgrade(leader);
+    }
+
     /// The [`ProcessGroup`]s that belong to this [`Session`].
     pub fn process_groups(&self) -> Vec<Arc<ProcessGroup>> {
         self.process_groups.lock().values().collect()
     }
 
+    /// The live [`Process`]es in all [`ProcessGroup`]s of this [`Session`].
+    pub fn processes(&self) -> Vec<Arc<Process>> {
+        self.process_groups()
+            .iter()
+            .flat_map(|g| g.processes())
+            .collect()
+    }
+
+    /// The number of live processes in all [`ProcessGroup`]s of this
+    /// [`Session`].
+    pub fn process_count(&self) -> usize {
+        self.process_groups().iter().map(|g| g.len()).sum()
+    }
+
+    /// The [`ProcessGroup`]s that belong to this [`Session`] but have no
+    /// members left.
+    ///
+    /// Such a [`ProcessGroup`] only stays alive while someone still holds a
+    /// reference to it.
+    pub fn empty_groups(&self) -> Vec<Arc<ProcessGroup>> {
+        self.process_groups()
+            .into_iter()
+            .filter(|g| g.is_empty())
+            .collect()
+    }
+
     /// Sets the terminal for this session.
     pub fn set_terminal_with(&self, terminal: impl FnOnce() -> Arc<dyn Any + Send + Sync>) -> bool {
         let mut guard = self.terminal.lock();
@@ -65,6 +135,67 @@ impl Session {
     }
 }
 
+/// Foreground process group
+impl Session {
+    /// Sets the foreground [`ProcessGroup`] of this session.
+    ///
+    /// Returns `false` if the [`ProcessGroup`] does not belong to this
+    /// session.
+    pub fn set_foreground_group(&self, group: &Arc<ProcessGroup>) -> bool {
+        if !ptr::eq(&*group.session, self) {
+            return false;
+        }
+        *self.foreground.lock() = Arc::downgrade(group);
+        true
+    }
+
+    /// Gets the foreground [`ProcessGroup`] of this session, if it is set and
+    /// still alive.
+    pub fn foreground_group(&self) -> Option<Arc<ProcessGroup>> {
+        self.foreground.lock().upgrade()
+    }
+
+    /// Handles the exit of the leader of this session.
+    ///
+    /// The terminal and the foreground [`ProcessGroup`] are cleared, and the
+    /// [`ProcessGroup`]s that should be hung up are returned. This does nothing
+    /// and returns an empty list if `process` is not the session leader.
+    ///
+    /// This is called by [`Process::exit`], which passes the returned
+    /// [`ProcessGroup`]s to the [`on_hangup`](crate::on_hangup) hook.
+    pub(crate) fn on_leader_exit(&self, process: &Process) -> Vec<Arc<ProcessGroup>> {
+        if process.pid() != self.sid || !ptr::eq(&*process.group().session, self) {
+            return Vec::new();
+        }
+
+        *self.terminal.lock() = None;
+        let foreground = core::mem::take(&mut *self.foreground.lock());
+        foreground.upgrade().into_iter().collect()
+    }
+}
+
+/// Associated data
+impl Session {
+    /// Sets the data associated with this session.
+    ///
+    /// The data can only be set once. Returns `false` if it was already set.
+    pub fn set_data<T: Any + Send + Sync>(&self, data: T) -> bool {
+        self.data.call_once(|| Box::new(data)).is_some()
+    }
+
+    /// Gets the data associated with this session, if it is set and of type
+    /// `T`.
+    pub fn data<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.data.get()?.downcast_ref()
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        table::prune_session(self.sid);
+    }
+}
+
 impl fmt::Debug for Session {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Session({})", self.sid)
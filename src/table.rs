@@ -0,0 +1,193 @@
+use alloc::{
+    collections::btree_map::BTreeMap,
+    sync::{Arc, Weak},
+    vec::Vec,
+};
+use core::ptr;
+
+use kspin::{SpinNoIrq, SpinNoIrqGuard};
+use lazyinit::LazyInit;
+use weak_map::WeakMap;
+
+use crate::{Pid, PidInUse, Process, ProcessGroup, Session};
+
+/// A locked [`process_table`].
+pub type ProcessTableGuard = SpinNoIrqGuard<'static, BTreeMap<Pid, Arc<Process>>>;
+
+/// A locked [`process_group_table`].
+pub type ProcessGroupTableGuard = SpinNoIrqGuard<'static, WeakMap<Pid, Weak<ProcessGroup>>>;
+
+/// A locked [`session_table`].
+pub type SessionTableGuard = SpinNoIrqGuard<'static, WeakMap<Pid, Weak<Session>>>;
+
+static PROCESS_TABLE: SpinNoIrq<BTreeMap<Pid, Arc<Process>>> = SpinNoIrq::new(BTreeMap::new());
+
+static PROCESS_GROUP_TABLE: SpinNoIrq<WeakMap<Pid, Weak<ProcessGroup>>> =
+    SpinNoIrq::new(WeakMap::new());
+
+static SESSION_TABLE: SpinNoIrq<WeakMap<Pid, Weak<Session>>> = SpinNoIrq::new(WeakMap::new());
+
+static INIT_PROCESS: LazyInit<Arc<Process>> = LazyInit::new();
+
+/// Registers the init [`Process`], which inherits orphaned processes.
+///
+/// This is done by [`Process::new_init`]. The init [`Process`] can only be
+/// registered once. Returns `false` if it was already registered.
+pub fn set_init_process(process: Arc<Process>) -> bool {
+    INIT_PROCESS.call_once(|| process).is_some()
+}
+
+/// Gets the registered init [`Process`], or `None` if it has not been
+/// registered yet.
+pub fn init_process() -> Option<Arc<Process>> {
+    INIT_PROCESS.get().cloned()
+}
+
+/// Gets a reference to the registered init [`Process`] without touching its
+/// reference count.
+pub(crate) fn init_process_ref() -> Option<&'static Arc<Process>> {
+    INIT_PROCESS.get()
+}
+
+/// Locks the table of all [`Process`]es, keyed by [`Process`] ID.
+pub fn process_table() -> ProcessTableGuard {
+    PROCESS_TABLE.lock()
+}
+
+/// Locks the table of all [`ProcessGroup`]s, keyed by [`ProcessGroup`] ID.
+pub fn process_group_table() -> ProcessGroupTableGuard {
+    PROCESS_GROUP_TABLE.lock()
+}
+
+/// Locks the table of all [`Session`]s, keyed by [`Session`] ID.
+pub fn session_table() -> SessionTableGuard {
+    SESSION_TABLE.lock()
+}
+
+/// Looks up a [`Process`] by its ID.
+///
+/// The table is only locked during the lookup.
+pub fn get_process(pid: Pid) -> Option<Arc<Process>> {
+    process_table().get(&pid).cloned()
+}
+
+/// Looks up a [`ProcessGroup`] by its ID.
+///
+/// The table is only locked during the lookup.
+pub fn get_process_group(pgid: Pid) -> Option<Arc<ProcessGroup>> {
+    process_group_table().get(&pgid)
+}
+
+/// Looks up a [`Session`] by its ID.
+///
+/// The table is only locked during the lookup.
+pub fn get_session(sid: Pid) -> Option<Arc<Session>> {
+    session_table().get(&sid)
+}
+
+/// Looks up a [`ProcessGroup`] by its ID and
+/// [generation](ProcessGroup::generation).
+///
+/// Returns `None` if the ID is now used by a different [`ProcessGroup`], so
+/// that a saved handle never resolves to a recycled ID.
+pub fn get_process_group_gen(pgid: Pid, generation: u64) -> Option<Arc<ProcessGroup>> {
+    get_process_group(pgid).filter(|g| g.generation() == generation)
+}
+
+/// Looks up a [`Session`] by its ID and [generation](Session::generation).
+///
+/// Returns `None` if the ID is now used by a different [`Session`], so that
+/// a saved handle never resolves to a recycled ID.
+pub fn get_session_gen(sid: Pid, generation: u64) -> Option<Arc<Session>> {
+    get_session(sid).filter(|s| s.generation() == generation)
+}
+
+/// All [`ProcessGroup`]s that still have members and are
+/// [orphaned](ProcessGroup::is_orphaned).
+pub fn orphaned_groups() -> Vec<Arc<ProcessGroup>> {
+    let groups: Vec<_> = process_group_table().values().collect();
+    groups
+        .into_iter()
+        .filter(|g| !g.is_empty() && g.is_orphaned())
+        .collect()
+}
+
+pub(crate) fn insert_process(process: &Arc<Process>) {
+    process_table().insert(process.pid(), process.clone());
+}
+
+/// Inserts a [`Process`] unless its [`Pid`] is already in use.
+pub(crate) fn try_insert_process(process: &Arc<Process>) -> Result<(), PidInUse> {
+    let mut table = process_table();
+    if table.contains_key(&process.pid()) {
+        return Err(PidInUse(process.pid()));
+    }
+    table.insert(process.pid(), process.clone());
+    Ok(())
+}
+
+/// Removes a [`Process`] from the process table.
+///
+/// Returns `false` if it was not in the table, e.g. because it has already
+/// been removed.
+pub(crate) fn remove_process(process: &Process) -> bool {
+    let mut table = process_table();
+    let removed = if table
+        .get(&process.pid())
+        .is_some_and(|p| ptr::eq(&**p, process))
+    {
+        table.remove(&process.pid())
+    } else {
+        None
+    };
+    drop(table);
+    removed.is_some()
+}
+
+pub(crate) fn insert_process_group(group: &Arc<ProcessGroup>) {
+    let mut table = process_group_table();
+    debug_assert!(
+        !table.contains_key(&group.pgid()),
+        "process group {} is still live",
+        group.pgid()
+    );
+    let old = table.insert(group.pgid(), group);
+    drop(table);
+    drop(old);
+}
+
+/// Prunes the entry of a dropped [`ProcessGroup`].
+///
+/// This is skipped if the table is locked, as the group may be dropped while
+/// the table is being accessed. The entry is then cleaned up lazily.
+pub(crate) fn prune_process_group(pgid: Pid) {
+    if let Some(mut table) = PROCESS_GROUP_TABLE.try_lock()
+        && !table.contains_key(&pgid)
+    {
+        table.remove(&pgid);
+    }
+}
+
+pub(crate) fn insert_session(session: &Arc<Session>) {
+    let mut table = session_table();
+    debug_assert!(
+        !table.contains_key(&session.sid()),
+        "session {} is still live",
+        session.sid()
+    );
+    let old = table.insert(session.sid(), session);
+    drop(table);
+    drop(old);
+}
+
+/// Prunes the entry of a dropped [`Session`].
+///
+/// This is skipped if the table is locked, as the session may be dropped
+/// while the table is being accessed. The entry is then cleaned up lazily.
+pub(crate) fn prune_session(sid: Pid) {
+    if let Some(mut table) = SESSION_TABLE.try_lock()
+        && !table.contains_key(&sid)
+    {
+        table.remove(&sid);
+    }
+}
@@ -0,0 +1,23 @@
+// This binary never creates the init process, as for kernel tasks that run
+// before init is set up.
+
+use std::sync::Arc;
+
+use starry_process::{Process, get_process, init_process};
+
+#[test]
+fn exit_before_init() {
+    assert!(init_process().is_none());
+
+    let detached = Process::new_detached(1);
+    let child = detached.fork(2);
+    assert!(detached.reaper().is_none());
+    assert!(child.reaper().is_none());
+
+    detached.exit();
+    assert!(detached.is_zombie());
+    assert!(Arc::ptr_eq(&detached, &child.parent().unwrap()));
+
+    detached.free();
+    assert!(get_process(1).is_none());
+}
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 
-use starry_process::init_proc;
+use starry_process::{ProcessState, get_process_group, init_proc};
 
 mod common;
 use common::ProcessExt;
@@ -139,3 +139,333 @@ fn cleanup_processes() {
 
     assert!(group.processes().is_empty());
 }
+
+#[test]
+fn generation() {
+    let parent = init_proc();
+    let group = parent.group();
+
+    let child = parent.new_child();
+    let old_generation = child.create_group().unwrap().generation();
+
+    assert!(child.move_to_group(&group));
+    let new_group = child.create_group().unwrap();
+
+    assert_eq!(new_group.pgid(), child.pid());
+    assert_ne!(new_group.generation(), old_generation);
+}
+
+#[test]
+fn orphaned_by_exit() {
+    let leader = init_proc().new_child();
+    leader.create_session().unwrap();
+
+    let job = leader.new_child();
+    job.create_group().unwrap();
+    let member = job.new_child();
+
+    assert_eq!(leader.groups_orphaned_by_exit(), [job.pid()]);
+    assert_eq!(job.groups_orphaned_by_exit(), [job.pid()]);
+    assert!(member.groups_orphaned_by_exit().is_empty());
+}
+
+#[test]
+fn subtree_members() {
+    let root = init_proc().new_child();
+    let group = root.create_group().unwrap();
+
+    let child1 = root.new_child();
+    let child2 = root.new_child();
+    child2.create_group().unwrap();
+    let grandchild1 = child2.new_child();
+    assert!(grandchild1.move_to_group(&group));
+    let grandchild2 = child2.new_child();
+
+    let members: Vec<_> = root
+        .subtree_members_of_group(&group)
+        .iter()
+        .map(|p| p.pid())
+        .collect();
+    assert_eq!(members, [root.pid(), child1.pid(), grandchild1.pid()]);
+
+    let members = child2.subtree_members_of_group(&child2.group());
+    assert_eq!(members.len(), 2);
+    assert!(members.iter().any(|p| Arc::ptr_eq(p, &grandchild2)));
+}
+
+#[test]
+#[cfg(debug_assertions)]
+#[should_panic]
+fn pid_reuse() {
+    let child = init_proc().new_child();
+    child.create_group().unwrap();
+    child.fork(child.pid());
+}
+
+#[test]
+fn create_group_live_id() {
+    let parent = init_proc().new_child();
+    let leader = parent.new_child();
+    let group = leader.create_group().unwrap();
+    let member = leader.new_child();
+
+    assert!(leader.move_to_group(&parent.group()));
+    assert!(leader.create_group().is_none());
+    assert!(Arc::ptr_eq(&group, &member.group()));
+    assert!(Arc::ptr_eq(
+        &group,
+        &get_process_group(leader.pid()).unwrap()
+    ));
+}
+
+#[test]
+fn same_session() {
+    let parent = init_proc().new_child();
+    let group = parent.group();
+
+    let child = parent.new_child();
+    let child_group = child.create_group().unwrap();
+    assert!(group.same_session(&child_group));
+
+    let (_, new_group) = parent.create_session().unwrap();
+    assert!(!new_group.same_session(&child_group));
+}
+
+#[test]
+fn threads() {
+    let parent = init_proc().new_child();
+    parent.create_group().unwrap();
+    let child = parent.new_child();
+
+    parent.add_thread(parent.pid());
+    parent.add_thread(parent.pid() + 1000);
+    child.add_thread(child.pid());
+    child.add_thread(child.pid() + 1000);
+
+    let mut threads = parent.group().threads();
+    threads.sort();
+    let mut expected = [
+        parent.pid(),
+        parent.pid() + 1000,
+        child.pid(),
+        child.pid() + 1000,
+    ];
+    expected.sort();
+    assert_eq!(threads, expected);
+}
+
+#[test]
+fn concurrent_moves() {
+    let leader = init_proc().new_child();
+    let (_, group) = leader.create_session().unwrap();
+    let other = leader.new_child();
+    let other_group = other.create_group().unwrap();
+
+    let movers: Vec<_> = (0..8).map(|_| leader.new_child()).collect();
+    let handles: Vec<_> = movers
+        .iter()
+        .cloned()
+        .map(|p| {
+            let group = group.clone();
+            let other_group = other_group.clone();
+            std::thread::spawn(move || {
+                for _ in 0..1000 {
+                    assert!(p.move_to_group(&other_group));
+                    assert!(p.move_to_group(&group));
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    assert_eq!(group.processes().len(), movers.len() + 1);
+    assert_eq!(other_group.processes().len(), 1);
+}
+
+#[test]
+fn data() {
+    struct JobStatus {
+        stopped: bool,
+    }
+
+    let child = init_proc().new_child();
+    let group = child.create_group().unwrap();
+    assert!(group.data::<JobStatus>().is_none());
+
+    assert!(group.set_data(JobStatus { stopped: true }));
+    assert!(!group.set_data(JobStatus { stopped: false }));
+    assert!(group.data::<JobStatus>().unwrap().stopped);
+    assert!(group.data::<u32>().is_none());
+}
+
+#[test]
+fn stop_continue() {
+    let parent = init_proc().new_child();
+    let group = parent.create_group().unwrap();
+    let child = parent.new_child();
+    let zombie = parent.new_child();
+    zombie.exit();
+
+    assert_eq!(group.stop_all(), 2);
+    assert_eq!(parent.state(), ProcessState::Stopped);
+    assert_eq!(child.state(), ProcessState::Stopped);
+    assert_eq!(zombie.state(), ProcessState::Zombie);
+    assert_eq!(group.stop_all(), 0);
+
+    assert_eq!(group.continue_all(), 2);
+    assert_eq!(parent.state(), ProcessState::Running);
+    assert_eq!(child.state(), ProcessState::Running);
+    assert_eq!(zombie.state(), ProcessState::Zombie);
+}
+
+#[test]
+fn orphaned() {
+    let leader = init_proc().new_child();
+    let (_, leader_group) = leader.create_session().unwrap();
+    assert!(leader_group.is_orphaned());
+
+    let job = leader.new_child();
+    let job_group = job.create_group().unwrap();
+    job.new_child();
+    assert!(!job_group.is_orphaned());
+
+    assert!(leader.move_to_group(&job_group));
+    assert!(job_group.is_orphaned());
+}
+
+#[test]
+fn len() {
+    let parent = init_proc().new_child();
+    let group = parent.create_group().unwrap();
+    let child = parent.new_child();
+    assert_eq!(group.len(), 2);
+    assert!(!group.is_empty());
+
+    for process in [child, parent] {
+        process.exit();
+        process.free();
+    }
+    assert_eq!(group.len(), 0);
+    assert!(group.is_empty());
+}
+
+#[test]
+fn for_each_process() {
+    let parent = init_proc().new_child();
+    let group = parent.create_group().unwrap();
+    let alive = parent.new_child();
+    let dead = parent.new_child();
+    dead.exit();
+    dead.free();
+    drop(dead);
+
+    let mut seen = Vec::new();
+    group.for_each_process(|p| {
+        // Would deadlock if the group's lock were still held.
+        assert_eq!(group.len(), 2);
+        seen.push(p.pid());
+    });
+    seen.sort();
+    assert_eq!(seen, [parent.pid(), alive.pid()]);
+}
+
+#[test]
+fn move_to_group_concurrent() {
+    let leader = init_proc().new_child();
+    leader.create_session().unwrap();
+    let group1 = leader.group();
+    let group2 = leader.new_child().create_group().unwrap();
+    let movers: Vec<_> = (0..4).map(|_| leader.new_child()).collect();
+    let total = group1.len() + group2.len();
+
+    let threads: Vec<_> = movers
+        .iter()
+        .enumerate()
+        .map(|(i, process)| {
+            let process = process.clone();
+            let (from, to) = if i % 2 == 0 {
+                (group1.clone(), group2.clone())
+            } else {
+                (group2.clone(), group1.clone())
+            };
+            std::thread::spawn(move || {
+                for _ in 0..1000 {
+                    assert!(process.move_to_group(&to));
+                    assert!(process.move_to_group(&from));
+                    let (from_len, to_len) = from.len_pair(&to);
+                    assert_eq!(from_len + to_len, total);
+                }
+            })
+        })
+        .collect();
+    let reader = {
+        let (group1, group2) = (group1.clone(), group2.clone());
+        std::thread::spawn(move || {
+            while !threads.iter().all(|t| t.is_finished()) {
+                let (len1, len2) = group1.len_pair(&group2);
+                assert_eq!(len1 + len2, total);
+            }
+            for thread in threads {
+                thread.join().unwrap();
+            }
+        })
+    };
+    reader.join().unwrap();
+
+    assert_eq!(group1.len() + group2.len(), total);
+    for process in &movers {
+        let group = process.group();
+        assert!(group.processes().iter().any(|p| Arc::ptr_eq(p, process)));
+    }
+}
+
+#[test]
+fn move_to_group_during_exit() {
+    for _ in 0..200 {
+        let parent = init_proc().new_child();
+        parent.create_session().unwrap();
+        let group1 = parent.group();
+        let child = parent.new_child();
+        let group2 = child.new_child().create_group().unwrap();
+
+        let mover = {
+            let child = child.clone();
+            std::thread::spawn(move || {
+                for _ in 0..50 {
+                    assert!(child.move_to_group(&group2));
+                    assert!(child.move_to_group(&group1));
+                }
+            })
+        };
+        let exiter = {
+            let parent = parent.clone();
+            let child = child.clone();
+            std::thread::spawn(move || {
+                child.exit();
+                parent.exit();
+            })
+        };
+        mover.join().unwrap();
+        exiter.join().unwrap();
+
+        assert!(Arc::ptr_eq(&child.group(), &parent.group()));
+        assert!(Arc::ptr_eq(&init_proc(), &child.parent().unwrap()));
+    }
+}
+
+#[test]
+fn leader() {
+    let leader = init_proc().new_child();
+    let group = leader.create_group().unwrap();
+    let member = leader.new_child();
+    assert!(Arc::ptr_eq(&group.leader().unwrap(), &leader));
+
+    leader.exit();
+    leader.free();
+    drop(leader);
+    assert!(group.leader().is_none());
+    assert!(!group.is_empty());
+    assert!(Arc::ptr_eq(&member.group(), &group));
+}
@@ -0,0 +1,99 @@
+use std::sync::Mutex;
+
+use starry_process::{Pid, init_proc, on_child_exit, on_hangup, on_reparent};
+
+mod common;
+use common::ProcessExt;
+
+static REPARENTED: Mutex<Vec<(Pid, Pid)>> = Mutex::new(Vec::new());
+static EXITED: Mutex<Vec<(Pid, Pid)>> = Mutex::new(Vec::new());
+static HUNG_UP: Mutex<Vec<Pid>> = Mutex::new(Vec::new());
+
+fn register_hooks() {
+    on_reparent(|parent, child| REPARENTED.lock().unwrap().push((parent.pid(), child.pid())));
+    on_child_exit(|parent, child| EXITED.lock().unwrap().push((parent.pid(), child.pid())));
+    on_hangup(|group| HUNG_UP.lock().unwrap().push(group.pgid()));
+}
+
+#[test]
+fn reparent() {
+    register_hooks();
+
+    let subreaper = init_proc().new_child();
+    subreaper.set_child_subreaper(true);
+    let parent = subreaper.new_child();
+    let mut children: Vec<_> = (0..3).map(|_| parent.new_child().pid()).collect();
+    parent.exit();
+
+    let mut reparented: Vec<_> = REPARENTED
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|(p, _)| *p == subreaper.pid())
+        .map(|(_, c)| *c)
+        .collect();
+    reparented.sort();
+    children.sort();
+    assert_eq!(reparented, children);
+}
+
+#[test]
+fn child_exit() {
+    register_hooks();
+
+    let init = init_proc();
+    let parent = init.new_child();
+    let child = parent.new_child();
+    let grandchild = child.new_child();
+    child.exit();
+    parent.exit();
+
+    let exited = EXITED.lock().unwrap();
+    assert!(exited.contains(&(parent.pid(), child.pid())));
+    assert!(exited.contains(&(init.pid(), parent.pid())));
+    assert!(!exited.iter().any(|(_, c)| *c == grandchild.pid()));
+}
+
+#[test]
+fn exit_once() {
+    register_hooks();
+
+    let parent = init_proc().new_child();
+    let process = parent.new_child();
+    let child = process.new_child();
+    let pid = process.pid();
+    process.add_thread(pid);
+    assert!(process.exit_thread(pid, 0));
+    assert!(process.is_zombie());
+    process.exit();
+
+    let count = |events: &Mutex<Vec<(Pid, Pid)>>, pid| {
+        events
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, c)| *c == pid)
+            .count()
+    };
+    assert_eq!(count(&EXITED, process.pid()), 1);
+    assert_eq!(count(&REPARENTED, child.pid()), 1);
+}
+
+#[test]
+fn hangup() {
+    register_hooks();
+
+    let leader = init_proc().new_child();
+    let (session, _) = leader.create_session().unwrap();
+    let job = leader.new_child();
+    let job_group = job.create_group().unwrap();
+    assert!(session.set_foreground_group(&job_group));
+
+    job.exit();
+    assert!(!HUNG_UP.lock().unwrap().contains(&job.pid()));
+
+    leader.exit();
+    let hung_up = HUNG_UP.lock().unwrap();
+    assert_eq!(hung_up.iter().filter(|&&g| g == job.pid()).count(), 1);
+    assert!(!hung_up.contains(&leader.pid()));
+}
@@ -0,0 +1,60 @@
+use starry_process::{PidAllocator, Process, get_process, get_process_group, get_session};
+
+#[test]
+fn exhaustion() {
+    let allocator = PidAllocator::new(4);
+    let pids: Vec<_> = (0..4).map(|_| allocator.alloc().unwrap()).collect();
+    assert_eq!(pids, [1, 2, 3, 4]);
+    assert!(allocator.alloc().is_none());
+}
+
+#[test]
+fn reuse_after_free() {
+    let allocator = PidAllocator::new(2);
+    assert_eq!(allocator.alloc(), Some(1));
+    assert_eq!(allocator.alloc(), Some(2));
+    allocator.free(1);
+    assert_eq!(allocator.alloc(), Some(1));
+    assert!(allocator.alloc().is_none());
+}
+
+#[test]
+fn wraparound() {
+    let allocator = PidAllocator::new(4);
+    for _ in 0..3 {
+        allocator.alloc();
+    }
+    allocator.free(2);
+    allocator.free(3);
+    assert_eq!(allocator.alloc(), Some(4));
+    assert_eq!(allocator.alloc(), Some(2));
+    assert_eq!(allocator.alloc(), Some(3));
+}
+
+#[test]
+fn skip_live() {
+    let init = Process::new_init(1000);
+    let allocator = PidAllocator::new(2000);
+    for _ in 1..999 {
+        allocator.alloc();
+    }
+    assert_eq!(allocator.alloc(), Some(999));
+    assert_eq!(allocator.alloc(), Some(1001));
+
+    // The leader is freed, but its group and session live on.
+    let leader = init.fork(1500);
+    leader.create_session().unwrap();
+    let _member = leader.fork(1501);
+    leader.exit();
+    leader.free();
+    assert!(get_process(1500).is_none());
+    assert!(get_process_group(1500).is_some());
+    assert!(get_session(1500).is_some());
+
+    let allocator = PidAllocator::new(1501);
+    for _ in 1..1498 {
+        allocator.alloc();
+    }
+    assert_eq!(allocator.alloc(), Some(1499));
+    assert!(allocator.alloc().is_none());
+}
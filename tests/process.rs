@@ -1,6 +1,9 @@
-use std::sync::Arc;
+use std::sync::{Arc, Barrier};
 
-use starry_process::init_proc;
+use starry_process::{
+    Credentials, NotZombie, PidInUse, Process, ProcessState, WaitStatus, init_proc, init_process,
+    set_init_process,
+};
 
 mod common;
 use common::ProcessExt;
@@ -28,6 +31,45 @@ fn free_not_zombie() {
     init_proc().new_child().free();
 }
 
+#[test]
+fn try_free() {
+    let parent = init_proc().new_child();
+    let child = parent.new_child();
+    assert_eq!(child.try_free(), Err(NotZombie));
+    assert_eq!(parent.children().len(), 1);
+
+    child.exit();
+    assert_eq!(child.try_free(), Ok(()));
+    assert!(parent.children().is_empty());
+    assert_eq!(child.try_free(), Err(NotZombie));
+}
+
+#[test]
+fn try_free_race() {
+    let parent = init_proc().new_child();
+    for _ in 0..100 {
+        let child = parent.new_child();
+        child.exit();
+
+        let barrier = Barrier::new(2);
+        let freed: usize = std::thread::scope(|s| {
+            let waiters: Vec<_> = (0..2)
+                .map(|_| {
+                    s.spawn(|| {
+                        barrier.wait();
+                        child.try_free().is_ok()
+                    })
+                })
+                .collect();
+            waiters
+                .into_iter()
+                .map(|w| w.join().unwrap() as usize)
+                .sum()
+        });
+        assert_eq!(freed, 1);
+    }
+}
+
 #[test]
 fn free() {
     let parent = init_proc().new_child();
@@ -47,3 +89,685 @@ fn reap() {
     parent.exit();
     assert!(Arc::ptr_eq(&init, &child.parent().unwrap()));
 }
+
+#[test]
+fn collapse_to_thread() {
+    let process = init_proc().new_child();
+    let pid = process.pid();
+    process.add_thread(pid);
+    process.add_thread(pid + 1000);
+    process.add_thread(pid + 1001);
+
+    assert!(process.collapse_to_thread(pid + 2000).is_none());
+    assert_eq!(process.thread_count(), 3);
+
+    let collapsed = process.collapse_to_thread(pid + 1001).unwrap();
+    assert_eq!(collapsed.threads, [pid + 1000]);
+    assert!(collapsed.leader_alive);
+    assert_eq!(process.threads(), [pid]);
+
+    process.add_thread(pid + 1002);
+    let collapsed = process.collapse_to_thread(pid).unwrap();
+    assert_eq!(collapsed.threads, [pid + 1002]);
+    assert!(!collapsed.leader_alive);
+    assert_eq!(process.threads(), [pid]);
+
+    process.add_thread(pid + 1003);
+    process.exit_thread(pid, 0);
+    let collapsed = process.collapse_to_thread(pid + 1003).unwrap();
+    assert!(collapsed.threads.is_empty());
+    assert!(!collapsed.leader_alive);
+    assert_eq!(process.threads(), [pid]);
+}
+
+#[test]
+fn terminate_and_reap() {
+    let init = init_proc();
+
+    let parent = init.new_child();
+    let process = parent.new_child();
+    let child = process.new_child();
+
+    process.terminate_and_reap(1);
+    assert!(process.is_zombie());
+    assert_eq!(process.exit_code(), 1);
+    assert!(parent.children().is_empty());
+    assert!(Arc::ptr_eq(&init, &child.parent().unwrap()));
+}
+
+#[test]
+fn reap_available() {
+    let parent = init_proc().new_child();
+    let running = parent.new_child();
+    for _ in 0..5 {
+        parent.new_child().exit();
+    }
+
+    assert_eq!(parent.reap_available(2).len(), 2);
+    assert_eq!(parent.reap_available(2).len(), 2);
+    assert_eq!(parent.reap_available(2).len(), 1);
+    assert!(parent.reap_available(2).is_empty());
+
+    let children = parent.children();
+    assert_eq!(children.len(), 1);
+    assert!(Arc::ptr_eq(&children[0], &running));
+}
+
+#[test]
+fn find_zombie_child() {
+    let parent = init_proc().new_child();
+    let running = parent.new_child();
+    assert!(parent.find_zombie_child().is_none());
+    assert!(parent.find_zombie_child_by_pid(running.pid()).is_none());
+
+    let zombie = parent.new_child();
+    zombie.exit();
+    assert!(Arc::ptr_eq(&zombie, &parent.find_zombie_child().unwrap()));
+    assert!(Arc::ptr_eq(
+        &zombie,
+        &parent.find_zombie_child_by_pid(zombie.pid()).unwrap()
+    ));
+    assert!(parent.find_zombie_child_by_pid(running.pid()).is_none());
+    assert_eq!(parent.children().len(), 2);
+
+    let found = parent.find_child(|c| c.pid() == running.pid()).unwrap();
+    assert!(Arc::ptr_eq(&running, &found));
+}
+
+#[test]
+fn wait_status_raw() {
+    let cases = [
+        (WaitStatus::Exited(0), 0x0000),
+        (WaitStatus::Exited(3), 0x0300),
+        (
+            WaitStatus::Signaled {
+                sig: 9,
+                core_dumped: false,
+            },
+            0x0009,
+        ),
+        (
+            WaitStatus::Signaled {
+                sig: 11,
+                core_dumped: true,
+            },
+            0x008b,
+        ),
+        (WaitStatus::Stopped(19), 0x137f),
+        (WaitStatus::Continued, 0xffff),
+    ];
+    for (status, raw) in cases {
+        assert_eq!(status.to_raw(), raw);
+        assert_eq!(WaitStatus::from_raw(raw), status);
+    }
+}
+
+#[test]
+fn wait_status() {
+    let process = init_proc().new_child();
+    let pid = process.pid();
+    process.add_thread(pid);
+    assert_eq!(process.wait_status(), None);
+
+    process.exit_thread(pid, 42);
+    assert_eq!(process.wait_status(), Some(WaitStatus::Exited(42)));
+}
+
+#[test]
+fn terminate() {
+    let killed = init_proc().new_child();
+    killed.terminate(WaitStatus::Signaled {
+        sig: 9,
+        core_dumped: false,
+    });
+    assert!(killed.is_zombie());
+    assert_eq!(
+        killed.wait_status(),
+        Some(WaitStatus::Signaled {
+            sig: 9,
+            core_dumped: false
+        })
+    );
+
+    let exited = init_proc().new_child();
+    exited.terminate(WaitStatus::Exited(0));
+    assert_eq!(exited.wait_status(), Some(WaitStatus::Exited(0)));
+    assert_ne!(killed.wait_status(), exited.wait_status());
+    assert_eq!(exited.exit_code(), 0);
+}
+
+#[test]
+fn terminate_after_exit() {
+    let process = init_proc().new_child();
+    process.group_exit(3);
+    process.terminate(WaitStatus::Exited(0));
+    assert_eq!(process.exit_code(), 3);
+    assert_eq!(process.wait_status(), Some(WaitStatus::Exited(3)));
+
+    process.terminate(WaitStatus::Signaled {
+        sig: 9,
+        core_dumped: false,
+    });
+    assert_eq!(process.wait_status(), Some(WaitStatus::Exited(3)));
+
+    let killed = init_proc().new_child();
+    killed.group_exit(0);
+    killed.terminate(WaitStatus::Signaled {
+        sig: 15,
+        core_dumped: false,
+    });
+    assert_eq!(
+        killed.wait_status(),
+        Some(WaitStatus::Signaled {
+            sig: 15,
+            core_dumped: false
+        })
+    );
+}
+
+#[test]
+fn zombie_leader() {
+    let process = init_proc().new_child();
+    let pid = process.pid();
+    process.add_thread(pid);
+    process.add_thread(pid + 1000);
+    assert!(!process.has_zombie_leader());
+
+    assert!(!process.exit_thread(pid, 0));
+    assert!(process.has_zombie_leader());
+    assert!(!process.is_zombie());
+
+    assert!(process.exit_thread(pid + 1000, 0));
+    assert!(!process.has_zombie_leader());
+}
+
+#[test]
+fn strong_count_hint() {
+    let parent = init_proc().new_child();
+    let child = parent.new_child();
+    assert_eq!(child.strong_count_hint(), 3);
+
+    child.exit();
+    child.free();
+    assert_eq!(child.strong_count_hint(), 1);
+}
+
+#[test]
+fn is_init() {
+    let init = init_proc();
+    assert!(init.is_init());
+    assert!(Arc::ptr_eq(&init, &init_process().unwrap()));
+
+    let child = init.new_child();
+    assert!(!child.is_init());
+    child.exit();
+    child.free();
+    assert!(!child.is_init());
+}
+
+#[test]
+fn start_seq() {
+    let parent = init_proc().new_child();
+    let child = parent.new_child();
+    assert!(init_proc().start_seq() < parent.start_seq());
+    assert!(parent.start_seq() < child.start_seq());
+}
+
+#[test]
+fn reaper() {
+    let init = init_proc();
+    assert!(init.reaper().is_none());
+
+    let child = init.new_child().new_child();
+    assert!(Arc::ptr_eq(&init, &child.reaper().unwrap()));
+}
+
+#[test]
+fn descendant_pids() {
+    let root = init_proc().new_child();
+    let child1 = root.new_child();
+    let child2 = root.new_child();
+    let grandchild = child1.new_child();
+
+    let pids: Vec<_> = root.descendant_pids().into_iter().collect();
+    assert_eq!(pids, [child1.pid(), child2.pid(), grandchild.pid()]);
+    assert!(child2.descendant_pids().is_empty());
+}
+
+#[test]
+fn descendants() {
+    let root = init_proc().new_child();
+    let child1 = root.new_child();
+    let child2 = root.new_child();
+    let grandchild1 = child1.new_child();
+    let grandchild2 = child2.new_child();
+
+    let pids: Vec<_> = root.descendants().iter().map(|p| p.pid()).collect();
+    assert_eq!(
+        pids,
+        [
+            child1.pid(),
+            child2.pid(),
+            grandchild1.pid(),
+            grandchild2.pid()
+        ]
+    );
+
+    let mut visited = Vec::new();
+    root.for_each_descendant(|p| visited.push(p.pid()));
+    assert_eq!(visited, pids);
+    assert!(grandchild1.descendants().is_empty());
+}
+
+#[test]
+fn ancestors() {
+    let init = init_proc();
+    let parent = init.new_child();
+    let child = parent.new_child();
+    let grandchild = child.new_child();
+
+    let pids: Vec<_> = grandchild.ancestors().map(|p| p.pid()).collect();
+    assert_eq!(pids, [child.pid(), parent.pid(), init.pid()]);
+    assert_eq!(init.ancestors().count(), 0);
+}
+
+#[test]
+fn loginuid() {
+    let parent = init_proc().new_child();
+    assert_eq!(parent.loginuid(), None);
+    assert!(parent.set_loginuid(1000, false));
+
+    let child = parent.new_child();
+    assert_eq!(child.loginuid(), Some(1000));
+
+    assert!(!child.set_loginuid(1001, false));
+    assert_eq!(child.loginuid(), Some(1000));
+    assert!(child.set_loginuid(1001, true));
+    assert_eq!(child.loginuid(), Some(1001));
+    assert_eq!(parent.loginuid(), Some(1000));
+}
+
+#[test]
+fn credentials() {
+    assert_eq!(init_proc().credentials(), Credentials::default());
+
+    let parent = init_proc().new_child();
+    let creds = Credentials {
+        ruid: 1000,
+        euid: 0,
+        rgid: 100,
+        egid: 100,
+    };
+    parent.set_credentials(creds);
+    assert_eq!(parent.credentials(), creds);
+
+    let child = parent.new_child();
+    assert_eq!(child.credentials(), creds);
+
+    child.set_credentials(Credentials {
+        euid: 1000,
+        ..creds
+    });
+    assert_eq!(child.credentials().euid, 1000);
+    assert_eq!(parent.credentials(), creds);
+}
+
+#[test]
+fn data() {
+    struct AddrSpace(usize);
+    struct FdTable(Vec<u32>);
+
+    let process = init_proc().new_child();
+    assert!(process.data::<AddrSpace>().is_none());
+
+    assert!(process.insert_data(AddrSpace(0x1000)).is_none());
+    assert!(process.insert_data(FdTable(vec![0, 1, 2])).is_none());
+    assert_eq!(process.data::<AddrSpace>().unwrap().with(|d| d.0), 0x1000);
+    assert_eq!(
+        process.data::<FdTable>().unwrap().with(|d| d.0.clone()),
+        [0, 1, 2]
+    );
+
+    let old = process.insert_data(AddrSpace(0x2000)).unwrap();
+    assert_eq!(old.with(|d| d.0), 0x1000);
+    assert_eq!(process.data::<AddrSpace>().unwrap().with(|d| d.0), 0x2000);
+
+    assert_eq!(
+        process
+            .remove_data::<FdTable>()
+            .unwrap()
+            .with(|d| d.0.clone()),
+        [0, 1, 2]
+    );
+    assert!(process.data::<FdTable>().is_none());
+    assert!(process.data::<AddrSpace>().is_some());
+    assert!(process.data::<u32>().is_none());
+}
+
+#[test]
+fn with_data_mut() {
+    // Deliberately not `Clone`.
+    struct Counter(u32);
+
+    let process = init_proc().new_child();
+    assert!(process.with_data_mut(|c: &mut Counter| c.0 += 1).is_none());
+
+    process.insert_data(Counter(0));
+    assert_eq!(process.with_data_mut(|c: &mut Counter| c.0 += 1), Some(()));
+    assert_eq!(process.data::<Counter>().unwrap().with(|d| d.0), 1);
+
+    let shared = process.data::<Counter>().unwrap();
+    process.with_data_mut(|c: &mut Counter| {
+        // Data of other types can be accessed while `Counter` is locked.
+        assert!(process.data::<u32>().is_none());
+        c.0 = 5;
+    });
+    assert_eq!(shared.with(|d| d.0), 5);
+    assert_eq!(process.data::<Counter>().unwrap().with(|d| d.0), 5);
+}
+
+#[test]
+fn try_fork() {
+    let parent = init_proc().new_child();
+    let child = parent.new_child();
+    assert_eq!(
+        parent.try_fork(child.pid()).unwrap_err(),
+        PidInUse(child.pid())
+    );
+
+    child.exit();
+    assert!(parent.try_fork(child.pid()).is_err());
+
+    child.free();
+    let pid = child.pid();
+    drop(child);
+    let reused = parent.try_fork(pid).unwrap();
+    assert!(Arc::ptr_eq(&parent, &reused.parent().unwrap()));
+}
+
+#[test]
+fn try_fork_concurrent() {
+    let parent = init_proc().new_child();
+    let child = parent.new_child();
+    let pid = child.pid();
+    child.exit();
+    child.free();
+    drop(child);
+
+    let threads: Vec<_> = (0..8)
+        .map(|_| {
+            let parent = parent.clone();
+            std::thread::spawn(move || parent.try_fork(pid).is_ok())
+        })
+        .collect();
+    let forked = threads
+        .into_iter()
+        .map(|t| t.join().unwrap())
+        .filter(|&ok| ok)
+        .count();
+    assert_eq!(forked, 1);
+    assert_eq!(parent.children().len(), 1);
+}
+
+#[test]
+fn exit_code_concurrent() {
+    let process = init_proc().new_child();
+    let pid = process.pid();
+    process.add_thread(pid);
+
+    let churn = {
+        let process = process.clone();
+        std::thread::spawn(move || {
+            for tid in pid + 1..pid + 1000 {
+                process.add_thread(tid);
+                process.exit_thread(tid, 1);
+            }
+        })
+    };
+    while !churn.is_finished() {
+        assert!(matches!(process.exit_code(), 0 | 1));
+    }
+    churn.join().unwrap();
+    assert_eq!(process.exit_code(), 1);
+
+    process.group_exit(1);
+    process.exit_thread(pid, 2);
+    assert_eq!(process.exit_code(), 1);
+}
+
+#[test]
+fn subreaper() {
+    let init = init_proc();
+
+    let subreaper = init.new_child();
+    subreaper.set_child_subreaper(true);
+    assert!(subreaper.is_child_subreaper());
+
+    let parent = subreaper.new_child();
+    let child = parent.new_child();
+    let grandchild = child.new_child();
+    assert!(Arc::ptr_eq(&subreaper, &grandchild.reaper().unwrap()));
+    assert!(Arc::ptr_eq(&init, &subreaper.reaper().unwrap()));
+
+    child.exit();
+    assert!(Arc::ptr_eq(&subreaper, &grandchild.parent().unwrap()));
+    assert!(
+        subreaper
+            .children()
+            .iter()
+            .any(|c| Arc::ptr_eq(c, &grandchild))
+    );
+
+    subreaper.set_child_subreaper(false);
+    parent.exit();
+    assert!(Arc::ptr_eq(&init, &child.parent().unwrap()));
+}
+
+#[test]
+fn subreaper_exit_race() {
+    let rounds: Vec<_> = (0..1000)
+        .map(|_| {
+            let subreaper = init_proc().new_child();
+            subreaper.set_child_subreaper(true);
+            let child = subreaper.new_child();
+            let grandchild = child.new_child();
+            (subreaper, child, grandchild)
+        })
+        .collect();
+
+    let barrier = Barrier::new(2);
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            for (subreaper, ..) in &rounds {
+                barrier.wait();
+                subreaper.exit();
+            }
+        });
+        s.spawn(|| {
+            for (_, child, _) in &rounds {
+                barrier.wait();
+                child.exit();
+            }
+        });
+    });
+
+    for (_, _, grandchild) in &rounds {
+        let reaper = grandchild.parent().unwrap();
+        assert!(!reaper.is_zombie());
+        assert!(reaper.children().iter().any(|c| Arc::ptr_eq(c, grandchild)));
+    }
+}
+
+#[test]
+fn reap_after_parent_exit() {
+    let init = init_proc();
+
+    let grandparent = init.new_child();
+    let parent = grandparent.new_child();
+    let child = parent.new_child();
+
+    grandparent.exit();
+    parent.exit();
+    assert!(Arc::ptr_eq(&init, &parent.parent().unwrap()));
+    assert!(Arc::ptr_eq(&init, &child.parent().unwrap()));
+}
+
+#[test]
+fn threads() {
+    let process = init_proc().new_child();
+    let pid = process.pid();
+    process.add_thread(pid);
+    process.add_thread(pid + 1000);
+    assert_eq!(process.threads(), [pid, pid + 1000]);
+
+    assert!(!process.exit_thread(pid + 1000, 0));
+    assert_eq!(process.threads(), [pid]);
+    assert!(process.exit_thread(pid, 0));
+    assert!(process.threads().is_empty());
+}
+
+#[test]
+fn thread_count() {
+    let process = init_proc().new_child();
+    let pid = process.pid();
+    assert_eq!(process.thread_count(), 0);
+
+    for tid in pid..pid + 3 {
+        process.add_thread(tid);
+    }
+    assert_eq!(process.thread_count(), 3);
+
+    process.exit_thread(pid + 1, 0);
+    assert_eq!(process.thread_count(), 2);
+    assert_eq!(process.threads(), [pid, pid + 2]);
+}
+
+#[test]
+fn children_where() {
+    let parent = init_proc().new_child();
+    let running = parent.new_child();
+    let zombie = parent.new_child();
+    zombie.exit();
+
+    let mut seen = Vec::new();
+    parent.children_where(|c| c.is_zombie(), |c| seen.push(c.pid()));
+    assert_eq!(seen, [zombie.pid()]);
+
+    seen.clear();
+    parent.children_where(|c| !c.is_zombie(), |c| seen.push(c.pid()));
+    assert_eq!(seen, [running.pid()]);
+}
+
+#[test]
+fn group_leader() {
+    let process = init_proc().new_child();
+    let pid = process.pid();
+    process.add_thread(pid + 1000);
+    assert_eq!(process.group_leader(), None);
+
+    process.add_thread(pid);
+    process.add_thread(pid + 1001);
+    assert_eq!(process.group_leader(), Some(pid));
+    assert!(process.is_group_leader(pid));
+    assert!(!process.is_group_leader(pid + 1000));
+
+    process.exit_thread(pid, 0);
+    assert_eq!(process.group_leader(), None);
+}
+
+#[test]
+fn exit_last_thread() {
+    let process = init_proc().new_child();
+    let pid = process.pid();
+    process.add_thread(pid);
+    process.add_thread(pid + 1000);
+
+    assert!(!process.exit_thread(pid + 1000, 0));
+    assert!(!process.is_zombie());
+
+    assert!(process.exit_thread(pid, 3));
+    assert!(process.is_zombie());
+    assert_eq!(process.exit_code(), 3);
+}
+
+#[test]
+fn group_exit() {
+    let process = init_proc().new_child();
+    let pid = process.pid();
+    process.add_thread(pid);
+    process.add_thread(pid + 1000);
+
+    assert_eq!(process.group_exit(2), [pid, pid + 1000]);
+    assert!(process.is_group_exited());
+    assert_eq!(process.exit_code(), 2);
+
+    process.group_exit(3);
+    assert!(!process.exit_thread(pid + 1000, 4));
+    assert!(process.exit_thread(pid, 5));
+    assert_eq!(process.exit_code(), 2);
+}
+
+#[test]
+fn group_exit_race() {
+    for _ in 0..100 {
+        let process = init_proc().new_child();
+        let pid = process.pid();
+        process.add_thread(pid);
+        process.add_thread(pid + 1000);
+
+        let thread_exit = {
+            let process = process.clone();
+            std::thread::spawn(move || process.exit_thread(pid + 1000, 1))
+        };
+        process.group_exit(2);
+        thread_exit.join().unwrap();
+
+        assert_eq!(process.exit_code(), 2);
+    }
+}
+
+#[test]
+fn state() {
+    let process = init_proc().new_child();
+    assert_eq!(process.state(), ProcessState::Running);
+    assert!(!process.set_running());
+
+    assert!(process.set_stopped());
+    assert_eq!(process.state(), ProcessState::Stopped);
+    assert!(!process.set_stopped());
+
+    assert!(process.set_running());
+    assert_eq!(process.state(), ProcessState::Running);
+
+    process.exit();
+    assert_eq!(process.state(), ProcessState::Zombie);
+    assert!(process.is_zombie());
+    assert!(!process.set_stopped());
+}
+
+#[test]
+#[should_panic]
+fn new_init_twice() {
+    Process::new_init(1 << 29);
+}
+
+#[test]
+fn set_init_process_once() {
+    let init = init_proc();
+    let child = init.new_child();
+    assert!(!set_init_process(child.clone()));
+    assert!(Arc::ptr_eq(&init, &init_proc()));
+    assert!(Arc::ptr_eq(&init, &child.new_child().reaper().unwrap()));
+}
+
+#[test]
+fn exited_subreaper() {
+    let subreaper = init_proc().new_child();
+    subreaper.set_child_subreaper(true);
+    let child = subreaper.new_child();
+    subreaper.exit();
+    assert!(!subreaper.is_child_subreaper());
+    subreaper.set_child_subreaper(true);
+    assert!(!subreaper.is_child_subreaper());
+    assert!(Arc::ptr_eq(&init_proc(), &child.reaper().unwrap()));
+}
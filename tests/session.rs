@@ -1,6 +1,9 @@
-use std::sync::Arc;
+use std::{any::Any, sync::Arc};
 
-use starry_process::init_proc;
+use starry_process::{
+    Placement, Process, get_process_group, get_session, init_proc, process_group_table,
+    process_table, session_table,
+};
 
 mod common;
 use common::ProcessExt;
@@ -106,3 +109,396 @@ fn cleanup_groups() {
 
     assert!(session.process_groups().is_empty());
 }
+
+#[test]
+fn session_ancestors() {
+    let outer = init_proc().new_child();
+    let leader = outer.new_child();
+    leader.create_session().unwrap();
+
+    let child = leader.new_child();
+    let grandchild = child.new_child();
+
+    let ancestors: Vec<_> = grandchild.session_ancestors().map(|p| p.pid()).collect();
+    assert_eq!(ancestors, [child.pid(), leader.pid()]);
+
+    assert_eq!(leader.session_ancestors().count(), 0);
+}
+
+#[test]
+fn generation() {
+    let child = init_proc().new_child();
+    let (session, _) = child.create_session().unwrap();
+    assert_ne!(
+        session.generation(),
+        init_proc().group().session().generation()
+    );
+}
+
+#[test]
+fn restore_placement() {
+    let leader = init_proc().new_child();
+    let (session, group) = leader.create_session().unwrap();
+
+    let child = leader.new_child();
+    child.create_group().unwrap();
+    let placement = child.placement();
+    assert_eq!(placement.pgid, child.pid());
+    assert_eq!(placement.sid, leader.pid());
+
+    assert!(child.move_to_group(&group));
+    assert_eq!(child.placement().pgid, leader.pid());
+
+    let restored = child
+        .restore_placement(placement, Some(&session), None)
+        .unwrap();
+    assert!(Arc::ptr_eq(&restored, &child.group()));
+    assert_eq!(child.placement(), placement);
+
+    assert!(
+        child
+            .restore_placement(placement, None, Some(&group))
+            .is_none()
+    );
+    assert!(Arc::ptr_eq(&restored, &child.group()));
+}
+
+#[test]
+fn restore_placement_new_session() {
+    let child = init_proc().new_child();
+    let placement = Placement {
+        pgid: child.pid(),
+        sid: child.pid(),
+    };
+
+    let group = child.restore_placement(placement, None, None).unwrap();
+    assert_eq!(child.placement(), placement);
+    assert_eq!(group.session().process_groups().len(), 1);
+}
+
+#[test]
+fn restore_placement_live_ids() {
+    let leader = init_proc().new_child();
+    let (session, group) = leader.create_session().unwrap();
+    let other = init_proc().new_child();
+    let (other_session, other_group) = other.create_session().unwrap();
+
+    let child = init_proc().new_child();
+    let placement = Placement {
+        pgid: group.pgid(),
+        sid: session.sid(),
+    };
+    let restored = child.restore_placement(placement, None, None).unwrap();
+    assert!(Arc::ptr_eq(&restored, &group));
+    assert!(Arc::ptr_eq(&child.group().session(), &session));
+
+    let conflicting = Placement {
+        pgid: other_group.pgid(),
+        sid: session.sid(),
+    };
+    assert!(child.restore_placement(conflicting, None, None).is_none());
+    assert!(Arc::ptr_eq(&child.group(), &group));
+
+    assert!(Arc::ptr_eq(&get_session(session.sid()).unwrap(), &session));
+    assert!(Arc::ptr_eq(
+        &get_session(other_session.sid()).unwrap(),
+        &other_session
+    ));
+    assert!(Arc::ptr_eq(
+        &get_process_group(other_group.pgid()).unwrap(),
+        &other_group
+    ));
+}
+
+#[test]
+fn empty_groups() {
+    let parent = init_proc();
+    let group = parent.group();
+    let session = group.session();
+
+    let child = parent.new_child();
+    let child_group = child.create_group().unwrap();
+    let is_listed = || {
+        session
+            .empty_groups()
+            .iter()
+            .any(|g| g.pgid() == child.pid())
+    };
+    assert!(!is_listed());
+
+    assert!(child.move_to_group(&group));
+    assert!(is_listed());
+
+    drop(child_group);
+    assert!(!is_listed());
+}
+
+#[test]
+fn controlling_terminal() {
+    let leader = init_proc().new_child();
+    let (session, _) = leader.create_session().unwrap();
+    let child = leader.new_child();
+    assert!(child.controlling_terminal().is_none());
+
+    let terminal: Arc<dyn Any + Send + Sync> = Arc::new(());
+    assert!(session.set_terminal_with(|| terminal.clone()));
+    assert!(Arc::ptr_eq(
+        &child.controlling_terminal().unwrap(),
+        &terminal
+    ));
+
+    assert!(session.unset_terminal(&terminal));
+    assert!(child.controlling_terminal().is_none());
+}
+
+#[test]
+fn session_leader() {
+    let leader = init_proc().new_child();
+    leader.create_session().unwrap();
+    let child = leader.new_child();
+    let grandchild = child.new_child();
+    grandchild.create_group().unwrap();
+
+    assert!(Arc::ptr_eq(&grandchild.session_leader().unwrap(), &leader));
+
+    leader.exit();
+    leader.free();
+    drop(leader);
+    assert!(child.session_leader().is_none());
+}
+
+#[test]
+fn leader() {
+    let leader = init_proc().new_child();
+    let (session, _) = leader.create_session().unwrap();
+    assert!(Arc::ptr_eq(&session.leader().unwrap(), &leader));
+    assert!(Arc::ptr_eq(
+        &init_proc().group().session().leader().unwrap(),
+        &init_proc()
+    ));
+
+    let child = leader.new_child();
+    leader.exit();
+    leader.free();
+    drop(leader);
+    assert!(session.leader().is_none());
+    assert!(child.session_leader().is_none());
+}
+
+#[test]
+fn sole_member_with_empty_group() {
+    let leader = init_proc().new_child();
+    let (session, _) = leader.create_session().unwrap();
+    let job = leader.new_child();
+    let job_group = job.create_group().unwrap();
+    assert!(session.set_foreground_group(&job_group));
+    assert!(!leader.is_sole_session_member());
+
+    job.exit();
+    job.free();
+    drop(job);
+    assert!(job_group.is_empty());
+    assert!(leader.is_sole_session_member());
+}
+
+#[test]
+fn sole_member() {
+    let leader = init_proc().new_child();
+    leader.create_session().unwrap();
+    assert!(leader.is_sole_group_member());
+    assert!(leader.is_sole_session_member());
+
+    let child = leader.new_child();
+    assert!(!leader.is_sole_group_member());
+    assert!(!child.is_sole_session_member());
+
+    child.create_group().unwrap();
+    assert!(child.is_sole_group_member());
+    assert!(!child.is_sole_session_member());
+}
+
+#[test]
+fn data() {
+    struct JobTable(Vec<u32>);
+
+    let child = init_proc().new_child();
+    let (session, _) = child.create_session().unwrap();
+    assert!(session.data::<JobTable>().is_none());
+
+    assert!(session.set_data(JobTable(vec![child.pid()])));
+    assert!(!session.set_data(JobTable(Vec::new())));
+    assert_eq!(session.data::<JobTable>().unwrap().0, [child.pid()]);
+    assert!(session.data::<u32>().is_none());
+}
+
+#[test]
+fn process_count() {
+    let leader = init_proc().new_child();
+    let (session, _) = leader.create_session().unwrap();
+    assert_eq!(session.process_count(), 1);
+
+    let job1 = leader.new_child();
+    job1.create_group().unwrap();
+    let member = job1.new_child();
+    let job2 = leader.new_child();
+    job2.create_group().unwrap();
+    assert_eq!(session.process_count(), 4);
+
+    member.exit();
+    member.free();
+    drop(member);
+    assert_eq!(session.process_count(), 3);
+}
+
+#[test]
+fn processes() {
+    let leader = init_proc().new_child();
+    let (session, _) = leader.create_session().unwrap();
+
+    let job1 = leader.new_child();
+    job1.create_group().unwrap();
+    let members: Vec<_> = (0..2).map(|_| job1.new_child()).collect();
+    let job2 = leader.new_child();
+    job2.create_group().unwrap();
+
+    let mut pids: Vec<_> = session.processes().iter().map(|p| p.pid()).collect();
+    pids.sort();
+    let mut expected = vec![leader.pid(), job1.pid(), job2.pid()];
+    expected.extend(members.iter().map(|p| p.pid()));
+    expected.sort();
+    assert_eq!(pids, expected);
+    assert_eq!(session.process_count(), 5);
+
+    job2.exit();
+    job2.free();
+    drop(job2);
+    assert_eq!(session.processes().len(), 4);
+    assert_eq!(session.process_count(), 4);
+}
+
+#[test]
+fn terminal() {
+    let child = init_proc().new_child();
+    let (session, _) = child.create_session().unwrap();
+
+    let terminal: Arc<dyn Any + Send + Sync> = Arc::new(());
+    assert!(session.set_terminal_with(|| terminal.clone()));
+    assert!(!session.set_terminal_with(|| Arc::new(())));
+    assert!(Arc::ptr_eq(&session.terminal().unwrap(), &terminal));
+
+    let weak = Arc::downgrade(&terminal);
+    drop(terminal);
+    drop(session);
+    child.exit();
+    child.free();
+    drop(child);
+    assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn foreground_group() {
+    let leader = init_proc().new_child();
+    let (session, group) = leader.create_session().unwrap();
+    assert!(session.foreground_group().is_none());
+
+    assert!(session.set_foreground_group(&group));
+    assert!(Arc::ptr_eq(&session.foreground_group().unwrap(), &group));
+
+    assert!(!session.set_foreground_group(&init_proc().group()));
+    assert!(Arc::ptr_eq(&session.foreground_group().unwrap(), &group));
+
+    let job = leader.new_child();
+    let job_group = job.create_group().unwrap();
+    assert!(session.set_foreground_group(&job_group));
+    drop(job_group);
+
+    job.exit();
+    job.free();
+    drop(job);
+    assert!(session.foreground_group().is_none());
+}
+
+#[test]
+fn leader_exit() {
+    let leader = init_proc().new_child();
+    let (session, group) = leader.create_session().unwrap();
+    let child = leader.new_child();
+    assert!(session.set_terminal_with(|| Arc::new(())));
+    assert!(session.set_foreground_group(&group));
+
+    child.exit();
+    assert!(session.terminal().is_some());
+    assert!(session.foreground_group().is_some());
+
+    leader.exit();
+    assert!(session.terminal().is_none());
+    assert!(session.foreground_group().is_none());
+}
+
+#[test]
+fn leader_exit_clears_terminal() {
+    let leader = init_proc().new_child();
+    let (session, _) = leader.create_session().unwrap();
+    assert!(session.set_terminal_with(|| Arc::new(())));
+
+    leader.exit();
+    assert!(session.terminal().is_none());
+}
+
+#[test]
+fn create_session_live_id() {
+    let init = init_proc();
+    let leader = init.new_child();
+    let (session, _) = leader.create_session().unwrap();
+    let member = leader.new_child();
+    leader.exit();
+    leader.free();
+
+    let reused = init.try_fork(leader.pid()).unwrap();
+    assert!(reused.create_session().is_none());
+    assert!(reused.create_group().is_none());
+    assert!(Arc::ptr_eq(&session, &get_session(leader.pid()).unwrap()));
+    assert!(Arc::ptr_eq(&session, &member.group().session()));
+}
+
+#[test]
+fn create_group_leader() {
+    let child = init_proc().new_child();
+    let group = child.create_group().unwrap();
+
+    assert!(child.create_session().is_none());
+    assert!(Arc::ptr_eq(&group, &child.group()));
+}
+
+#[test]
+fn detached() {
+    let pid = 1 << 30;
+    let detached = Process::new_detached(pid);
+    assert!(detached.is_detached());
+    assert!(!init_proc().is_detached());
+    assert!(detached.parent().is_none());
+    assert!(!detached.is_init());
+    assert!(
+        process_table()
+            .get(&pid)
+            .is_some_and(|p| Arc::ptr_eq(p, &detached))
+    );
+
+    let group = detached.group();
+    let session = group.session();
+    assert!(!Arc::ptr_eq(&session, &init_proc().group().session()));
+    assert!(
+        process_group_table()
+            .get(&group.pgid())
+            .is_none_or(|g| !Arc::ptr_eq(&g, &group))
+    );
+    let sessions: Vec<_> = session_table().values().collect();
+    assert!(!sessions.iter().any(|s| Arc::ptr_eq(s, &session)));
+
+    let child = detached.new_child();
+    assert!(child.is_detached());
+    detached.exit();
+    detached.free();
+    assert!(!process_table().contains_key(&pid));
+    assert!(Arc::ptr_eq(&init_proc(), &child.parent().unwrap()));
+}
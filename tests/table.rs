@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use starry_process::{
+    Pid, get_process, get_process_group, get_process_group_gen, get_session, get_session_gen,
+    init_proc, orphaned_groups, process_group_table, process_table, session_table,
+};
+
+mod common;
+use common::ProcessExt;
+
+#[test]
+fn keys() {
+    let child = init_proc().new_child();
+    child.create_session().unwrap();
+
+    assert!(process_table().iter().all(|(pid, p)| *pid == p.pid()));
+    assert!(
+        process_group_table()
+            .iter()
+            .all(|(pgid, g)| *pgid == g.pgid())
+    );
+    assert!(session_table().iter().all(|(sid, s)| *sid == s.sid()));
+}
+
+#[test]
+fn register() {
+    let child = init_proc().new_child();
+    let (session, group) = child.create_session().unwrap();
+
+    assert!(Arc::ptr_eq(&process_table()[&child.pid()], &child));
+    assert!(Arc::ptr_eq(
+        &process_group_table().get(&child.pid()).unwrap(),
+        &group
+    ));
+    assert!(Arc::ptr_eq(
+        &session_table().get(&child.pid()).unwrap(),
+        &session
+    ));
+}
+
+#[test]
+fn free() {
+    let child = init_proc().new_child();
+    let pid = child.pid();
+    child.create_session().unwrap();
+
+    child.exit();
+    child.free();
+    assert!(process_table().get(&pid).is_none());
+
+    drop(child);
+    assert!(process_group_table().get(&pid).is_none());
+    assert!(session_table().get(&pid).is_none());
+}
+
+#[test]
+fn lookup() {
+    let child = init_proc().new_child();
+    let pid = child.pid();
+    let (session, group) = child.create_session().unwrap();
+
+    assert!(Arc::ptr_eq(&get_process(pid).unwrap(), &child));
+    assert!(Arc::ptr_eq(&get_process_group(pid).unwrap(), &group));
+    assert!(Arc::ptr_eq(&get_session(pid).unwrap(), &session));
+
+    let unused = Pid::MAX;
+    assert!(get_process(unused).is_none());
+    assert!(get_process_group(unused).is_none());
+    assert!(get_session(unused).is_none());
+}
+
+#[test]
+fn orphaned() {
+    let leader = init_proc().new_child();
+    let (_, leader_group) = leader.create_session().unwrap();
+    let job = leader.new_child();
+    let job_group = job.create_group().unwrap();
+
+    let orphaned = orphaned_groups();
+    assert!(orphaned.iter().any(|g| Arc::ptr_eq(g, &leader_group)));
+    assert!(!orphaned.iter().any(|g| Arc::ptr_eq(g, &job_group)));
+}
+
+#[test]
+fn recycled_ids() {
+    let parent = init_proc().new_child();
+    let child = parent.new_child();
+    let pid = child.pid();
+    let (session, group) = child.create_session().unwrap();
+    let (sid_gen, pgid_gen) = (session.generation(), group.generation());
+    assert!(get_process_group_gen(pid, pgid_gen).is_some());
+    assert!(get_session_gen(pid, sid_gen).is_some());
+
+    child.exit();
+    child.free();
+    drop((child, session, group));
+
+    let child = parent.try_fork(pid).unwrap();
+    let (session, group) = child.create_session().unwrap();
+    assert!(get_process_group_gen(pid, pgid_gen).is_none());
+    assert!(get_session_gen(pid, sid_gen).is_none());
+    assert!(Arc::ptr_eq(
+        &get_process_group_gen(pid, group.generation()).unwrap(),
+        &group
+    ));
+    assert!(Arc::ptr_eq(
+        &get_session_gen(pid, session.generation()).unwrap(),
+        &session
+    ));
+}